@@ -1,6 +1,7 @@
 extern crate philipshue;
 use std::env;
 use philipshue::bridge::Bridge;
+use philipshue::color::ColorTemperature;
 
 mod discover;
 use discover::discover;
@@ -27,7 +28,7 @@ fn main() {
                          light.state.bri,
                          Show(&light.state.hue),
                          Show(&light.state.sat),
-                         Show(&light.state.ct.map(|ct| 1000000u32 / ct as u32)),
+                         Show(&light.state.ct.map(|ct| ColorTemperature::from_mired(ct).kelvin())),
                          light.state.alert,
                          Show(&light.state.effect),
                          Show(&light.state.colormode),
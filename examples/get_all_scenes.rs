@@ -20,7 +20,7 @@ fn main() {
                 scenes.values().map(|s| s.name.len()).max().unwrap_or(4)
             );
             let id_len = std::cmp::max(2,
-                scenes.keys().map(|id| id.len()).max().unwrap_or(2)
+                scenes.keys().map(|id| id.as_str().len()).max().unwrap_or(2)
             );
             println!("{0:2$} {1:3$} recycle locked appdata_and_version lights",
                      "id",
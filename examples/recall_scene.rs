@@ -4,6 +4,7 @@ use std::env;
 use std::num::ParseIntError;
 
 use philipshue::bridge::Bridge;
+use philipshue::hue::{GroupId, SceneId};
 
 mod discover;
 use discover::discover;
@@ -23,13 +24,13 @@ fn run() -> Result<(), ParseIntError> {
         return Ok(());
     }
     let bridge = Bridge::new(discover().pop().unwrap(), &*args[1]);
-    let group_id: usize = args[2].parse()?;
-    let scene = &*args[3];
+    let group_id: GroupId = args[2].parse()?;
+    let scene = SceneId::from(&*args[3]);
 
-    match bridge.recall_scene_in_group(group_id, scene) {
-        Ok(resps) => {
-            for resp in resps.into_iter() {
-                println!("{:?}", resp)
+    match bridge.recall_scene_in_group(group_id, &scene) {
+        Ok(resp) => {
+            for change in resp.changes {
+                println!("{} = {}", change.path, change.value)
             }
         }
         Err(e) => println!("Error occured when trying to send request:\n\t{}", e),
@@ -3,11 +3,12 @@ extern crate philipshue;
 use std::env;
 use std::num::ParseIntError;
 
-use philipshue::hue::LightCommand;
+use philipshue::hue::{LightCommand, GroupId};
+use philipshue::color::{Gamut, CtRange};
 use philipshue::bridge::Bridge;
 
 mod discover;
-use discover::{discover, rgb_to_hsv};
+use discover::discover;
 
 fn main() {
     match run() {
@@ -26,7 +27,7 @@ fn run() -> Result<(), ParseIntError> {
         return Ok(());
     }
     let bridge = Bridge::new(discover().pop().unwrap(), &*args[1]);
-    let group_id: usize = args[2].parse()?;
+    let group_id: GroupId = args[2].parse()?;
 
     let cmd = LightCommand::default();
 
@@ -42,8 +43,8 @@ fn run() -> Result<(), ParseIntError> {
                 .with_bri(args[6].parse()?)
         }
         "rgb" => {
-            let (hue, sat, bri) = rgb_to_hsv(args[4].parse()?, args[5].parse()?, args[6].parse()?);
-            cmd.with_hue(hue).with_sat(sat).with_bri(bri)
+            // A group can contain lights with different gamuts, so use the most common one
+            cmd.with_rgb(args[4].parse()?, args[5].parse()?, args[6].parse()?, &Gamut::C)
         }
         "mired" => {
             cmd.with_ct(args[4].parse()?)
@@ -51,7 +52,7 @@ fn run() -> Result<(), ParseIntError> {
                 .with_sat(254)
         }
         "kelvin" => {
-            cmd.with_ct((1000000u32 / args[4].parse::<u32>()?) as u16)
+            cmd.with_kelvin(args[4].parse()?, &CtRange::FULL)
                 .with_bri(args[5].parse()?)
                 .with_sat(254)
         }
@@ -59,9 +60,12 @@ fn run() -> Result<(), ParseIntError> {
     };
 
     match bridge.set_group_state(group_id, &cmd) {
-        Ok(resps) => {
-            for resp in resps.into_iter() {
-                println!("{:?}", resp)
+        Ok(resp) => {
+            for change in resp.changes {
+                println!("{} = {}", change.path, change.value)
+            }
+            for e in resp.errors {
+                println!("Could not change {}: {}", e.path, e.description)
             }
         }
         Err(e) => println!("Error occured when trying to send request:\n\t{}", e),
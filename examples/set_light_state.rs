@@ -1,14 +1,41 @@
 extern crate philipshue;
 
 use std::env;
-use std::time::Duration;
 use std::num::ParseIntError;
 
-use philipshue::hue::LightCommand;
+use philipshue::hue::{LightCommand, LightId};
 use philipshue::bridge::Bridge;
+use philipshue::errors;
+use philipshue::rate_limit::RateLimiter;
 
 mod discover;
-use discover::{discover, rgb_to_hsv};
+use discover::discover;
+
+/// What to set the lights to
+enum Setting {
+    /// The same command for every light
+    Command(LightCommand),
+    /// An RGB colour, converted with the gamut of each light
+    Rgb(u8, u8, u8),
+    /// A colour temperature in kelvin, limited to the range of each light, and a brightness
+    Kelvin(u32, u8),
+}
+
+impl Setting {
+    /// The command for a light, getting the light from the bridge only if its gamut or range is needed
+    fn command(&self, bridge: &Bridge, id: LightId) -> errors::Result<LightCommand> {
+        Ok(match *self {
+            Setting::Command(ref cmd) => cmd.clone(),
+            Setting::Rgb(r, g, b) => LightCommand::default().with_rgb(r, g, b, &bridge.get_light(id)?.gamut()),
+            Setting::Kelvin(kelvin, bri) => {
+                LightCommand::default()
+                    .with_kelvin(kelvin, &bridge.get_light(id)?.ct_range())
+                    .with_bri(bri)
+                    .with_sat(254)
+            }
+        })
+    }
+}
 
 fn main() {
     match run() {
@@ -25,51 +52,53 @@ fn run() -> Result<(), ParseIntError> {
                  args[0]);
         return Ok(());
     }
-    let bridge = Bridge::new(discover().pop().unwrap(), &*args[1]);
+    let bridge = Bridge::new(discover().pop().unwrap(), &*args[1]).with_rate_limit(RateLimiter::default());
     let input_lights = args[2].split(",")
         .fold(Ok(Vec::new()),
-              |v, s| v.and_then(|mut v| s.parse::<usize>().map(|n| v.push(n)).map(|_| v)))?;
+              |v, s| v.and_then(|mut v| s.parse::<LightId>().map(|n| v.push(n)).map(|_| v)))?;
 
     let cmd = LightCommand::default();
 
-    let cmd = match &*args[3] {
-        "on" => cmd.on(),
-        "off" => cmd.off(),
-        "bri" => cmd.with_bri(args[4].parse()?),
-        "hue" => cmd.with_hue(args[4].parse()?),
-        "sat" => cmd.with_sat(args[4].parse()?),
+    let setting = match &*args[3] {
+        "on" => Setting::Command(cmd.on()),
+        "off" => Setting::Command(cmd.off()),
+        "bri" => Setting::Command(cmd.with_bri(args[4].parse()?)),
+        "hue" => Setting::Command(cmd.with_hue(args[4].parse()?)),
+        "sat" => Setting::Command(cmd.with_sat(args[4].parse()?)),
         "hsv" => {
-            cmd.with_hue(args[4].parse()?)
+            Setting::Command(cmd.with_hue(args[4].parse()?)
                 .with_sat(args[5].parse()?)
-                .with_bri(args[6].parse()?)
-        }
-        "rgb" => {
-            let (hue, sat, bri) = rgb_to_hsv(args[4].parse()?, args[5].parse()?, args[6].parse()?);
-            cmd.with_hue(hue).with_sat(sat).with_bri(bri)
+                .with_bri(args[6].parse()?))
         }
+        "rgb" => Setting::Rgb(args[4].parse()?, args[5].parse()?, args[6].parse()?),
         "mired" => {
-            cmd.with_ct(args[4].parse()?)
-                .with_bri(args[5].parse()?)
-                .with_sat(254)
-        }
-        "kelvin" => {
-            cmd.with_ct((1000000u32 / args[4].parse::<u32>()?) as u16)
+            Setting::Command(cmd.with_ct(args[4].parse()?)
                 .with_bri(args[5].parse()?)
-                .with_sat(254)
+                .with_sat(254))
         }
+        "kelvin" => Setting::Kelvin(args[4].parse()?, args[5].parse()?),
         _ => return Ok(println!("Invalid command!")),
     };
 
     for id in input_lights.into_iter() {
+        let cmd = match setting.command(&bridge, id) {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                println!("Error occured when trying to get light {}:\n\t{}", id, e);
+                continue;
+            }
+        };
         match bridge.set_light_state(id, &cmd) {
-            Ok(resps) => {
-                for resp in resps.into_iter() {
-                    println!("{:?}", resp)
+            Ok(resp) => {
+                for change in resp.changes {
+                    println!("{} = {}", change.path, change.value)
+                }
+                for e in resp.errors {
+                    println!("Could not change {}: {}", e.path, e.description)
                 }
             }
             Err(e) => println!("Error occured when trying to send request:\n\t{}", e),
         }
-        std::thread::sleep(Duration::from_millis(50))
     }
 
     Ok(())
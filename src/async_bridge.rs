@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+use bridge::{self, Bridge};
+use errors::Result;
+use transport::{Transport, HyperTransport};
+use ::hue::*;
+
+/// How many threads the requests of all `AsyncBridge`s and the functions of this module share,
+/// unless an `AsyncBridge` is given its own with `AsyncBridge::with_threads()`
+pub const DEFAULT_THREADS: usize = 4;
+
+/// How long a thread of a pool waits for a request before it stops
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct PoolState {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+}
+
+/// Runs jobs on at most `max_threads` threads, queueing the jobs that don't fit.
+///
+/// Threads are started when there is a job and no idle thread, and stop after being idle for `IDLE_TIMEOUT`.
+struct Pool {
+    max_threads: usize,
+    state: Mutex<PoolState>,
+    queued: Condvar,
+}
+
+impl Pool {
+    fn new(max_threads: usize) -> Arc<Pool> {
+        Arc::new(Pool {
+            max_threads: max_threads.max(1),
+            state: Mutex::new(PoolState {
+                jobs: VecDeque::new(),
+                threads: 0,
+                idle: 0,
+            }),
+            queued: Condvar::new(),
+        })
+    }
+    /// The pool shared by everything that wasn't given its own
+    fn shared() -> Arc<Pool> {
+        static SHARED: OnceLock<Arc<Pool>> = OnceLock::new();
+        SHARED.get_or_init(|| Pool::new(DEFAULT_THREADS)).clone()
+    }
+    fn execute(self: &Arc<Self>, job: Job) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(job);
+        if state.idle == 0 && state.threads < self.max_threads {
+            state.threads += 1;
+            let pool = self.clone();
+            thread::spawn(move || pool.work());
+        } else {
+            self.queued.notify_one();
+        }
+    }
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                job();
+                state = self.state.lock().unwrap();
+                continue;
+            }
+            state.idle += 1;
+            let (s, timeout) = self.queued.wait_timeout(state, IDLE_TIMEOUT).unwrap();
+            state = s;
+            state.idle -= 1;
+            if timeout.timed_out() && state.jobs.is_empty() {
+                state.threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+struct Shared<R> {
+    result: Option<Result<R>>,
+    waker: Option<Waker>,
+}
+
+/// A request to the bridge that is being sent in the background.
+///
+/// It works with any executor, as the request is sent on a thread of a small pool.
+pub struct BridgeFuture<R> {
+    shared: Arc<Mutex<Shared<R>>>,
+}
+
+impl<R> Future for BridgeFuture<R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<R>> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Runs `f` on a thread of `pool`, resolving the returned future with its result
+fn spawn<R, F>(pool: &Arc<Pool>, f: F) -> BridgeFuture<R>
+    where R: Send + 'static,
+          F: FnOnce() -> Result<R> + Send + 'static
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    let theirs = shared.clone();
+    pool.execute(Box::new(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(f))
+            .unwrap_or_else(|_| Err("the request panicked".into()));
+        let mut shared = theirs.lock().unwrap();
+        shared.result = Some(result);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }));
+    BridgeFuture { shared }
+}
+
+/// Same as `bridge::register_user()`, but doesn't block
+pub fn register_user<S: Into<String>, D: Into<String>>(ip: S, devicetype: D) -> BridgeFuture<Username> {
+    let (ip, devicetype) = (ip.into(), devicetype.into());
+    spawn(&Pool::shared(), move || bridge::register_user(&ip, &devicetype))
+}
+
+/// Same as `bridge::register_user_https()`, but doesn't block
+#[cfg(feature = "https")]
+pub fn register_user_https<S, D, B>(ip: S, devicetype: D, bridgeid: B, pin: &::transport::CertificatePin)
+                                    -> BridgeFuture<Username>
+    where S: Into<String>,
+          D: Into<String>,
+          B: Into<String>
+{
+    let (ip, devicetype, bridgeid, pin) = (ip.into(), devicetype.into(), bridgeid.into(), pin.clone());
+    spawn(&Pool::shared(), move || bridge::register_user_https(&ip, &devicetype, &bridgeid, &pin))
+}
+
+/// Same as `bridge::discover()`, but doesn't block
+#[cfg(feature = "nupnp")]
+pub fn discover() -> BridgeFuture<Vec<Discovery>> {
+    spawn(&Pool::shared(), bridge::discover)
+}
+
+/// Same as `bridge::discover_upnp()`, but doesn't block
+#[cfg(feature = "ssdp")]
+pub fn discover_upnp() -> BridgeFuture<Vec<String>> {
+    spawn(&Pool::shared(),
+          || bridge::discover_upnp().map_err(|e| format!("UPnP discovery failed: {}", e).into()))
+}
+
+/// A non-blocking version of `Bridge`.
+///
+/// Every method has the same meaning as the `Bridge` method of the same name,
+/// except that it takes owned arguments and returns a `BridgeFuture`. Anything else
+/// can be done with the blocking `Bridge` through `call()`.
+///
+/// The requests are sent on a pool of `DEFAULT_THREADS` threads shared by all `AsyncBridge`s,
+/// and wait in a queue while all threads are busy. A slow request, such as `wait_for_new_lights()`,
+/// keeps a thread busy until it finishes, so give an `AsyncBridge` that sends those its own threads
+/// with `with_threads()`.
+pub struct AsyncBridge<T: Transport = HyperTransport> {
+    bridge: Arc<Bridge<T>>,
+    pool: Arc<Pool>,
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for AsyncBridge<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncBridge")
+            .field("bridge", &self.bridge)
+            .field("max_threads", &self.pool.max_threads)
+            .finish()
+    }
+}
+
+impl<T: Transport> Clone for AsyncBridge<T> {
+    fn clone(&self) -> Self {
+        AsyncBridge {
+            bridge: self.bridge.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl AsyncBridge {
+    /// Creates an `AsyncBridge` on the given IP with the given username
+    pub fn new<S: Into<String>, U: Into<Username>>(ip: S, username: U) -> Self {
+        AsyncBridge::from(Bridge::new(ip, username))
+    }
+}
+
+impl<T: Transport> From<Bridge<T>> for AsyncBridge<T> {
+    fn from(bridge: Bridge<T>) -> Self {
+        AsyncBridge {
+            bridge: Arc::new(bridge),
+            pool: Pool::shared(),
+        }
+    }
+}
+
+macro_rules! async_methods {
+    ($(fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty => |$b:ident| $call:expr;)*) => {
+        $(
+            #[doc = concat!("Same as `Bridge::", stringify!($name), "()`, but doesn't block")]
+            pub fn $name(&self $(, $arg: $ty)*) -> BridgeFuture<$ret> {
+                self.call(move |$b| $call)
+            }
+        )*
+    };
+}
+
+impl<T: Transport + Send + Sync + 'static> AsyncBridge<T> {
+    /// The blocking `Bridge` the requests are sent with
+    pub fn bridge(&self) -> &Bridge<T> {
+        &self.bridge
+    }
+    /// Sends the requests of this `AsyncBridge` and its clones on their own pool of at most `threads` threads
+    pub fn with_threads(self, threads: usize) -> Self {
+        AsyncBridge { pool: Pool::new(threads), ..self }
+    }
+    /// Calls `f` with the blocking `Bridge` on a thread of the pool, resolving the returned future with its result
+    pub fn call<R, F>(&self, f: F) -> BridgeFuture<R>
+        where R: Send + 'static,
+              F: FnOnce(&Bridge<T>) -> Result<R> + Send + 'static
+    {
+        let bridge = self.bridge.clone();
+        spawn(&self.pool, move || f(&bridge))
+    }
+
+    async_methods! {
+        // LIGHTS
+
+        fn get_all_lights() -> BTreeMap<LightId, Light> => |b| b.get_all_lights();
+        fn get_light(id: LightId) -> Light => |b| b.get_light(id);
+        fn get_new_lights() -> NewLights => |b| b.get_new_lights();
+        fn search_for_new_lights(deviceids: Vec<String>) -> CommandResponse => |b| {
+            let ids: Vec<&str> = deviceids.iter().map(|s| &**s).collect();
+            b.search_for_new_lights(&ids)
+        };
+        fn wait_for_new_lights(timeout: Duration) -> NewLights => |b| b.wait_for_new_lights(timeout);
+        fn set_light_state(id: LightId, command: LightCommand) -> CommandResponse => |b| b.set_light_state(id, &command);
+        fn rename_light(id: LightId, name: String) -> CommandResponse => |b| b.rename_light(id, name);
+        fn delete_light(id: LightId) -> Vec<String> => |b| b.delete_light(id);
+
+        // GROUPS
+
+        fn get_all_groups() -> BTreeMap<GroupId, Group> => |b| b.get_all_groups();
+        fn create_group(name: String, lights: Vec<LightId>, group_type: GroupType, room_class: Option<RoomClass>)
+            -> GroupId => |b| b.create_group(name, lights, group_type, room_class);
+        fn get_group_attributes(id: GroupId) -> Group => |b| b.get_group_attributes(id);
+        fn set_group_attributes(id: GroupId, attr: GroupCommand) -> CommandResponse => |b| b.set_group_attributes(id, &attr);
+        fn set_group_state(id: GroupId, state: LightCommand) -> CommandResponse => |b| b.set_group_state(id, &state);
+        fn delete_group(id: GroupId) -> Vec<String> => |b| b.delete_group(id);
+
+        // CONFIGURATION
+
+        fn get_configuration() -> Configuration => |b| b.get_configuration();
+        fn modify_configuration(command: ConfigurationModifier) -> CommandResponse => |b| b.modify_configuration(&command);
+        fn delete_user(username: Username) -> Vec<String> => |b| b.delete_user(&username);
+        fn get_capabilities() -> Capabilities => |b| b.get_capabilities();
+        fn get_full_state() -> FullState => |b| b.get_full_state();
+        fn recall_scene_in_group(group_id: GroupId, scene_id: SceneId) -> CommandResponse
+            => |b| b.recall_scene_in_group(group_id, &scene_id);
+
+        // SCENES
+
+        fn get_all_scenes() -> BTreeMap<SceneId, Scene> => |b| b.get_all_scenes();
+        fn create_scene(scene: SceneCreater) -> SceneId => |b| b.create_scene(&scene);
+        fn modify_scene(id: SceneId, scene: SceneModifier) -> CommandResponse => |b| b.modify_scene(&id, &scene);
+        fn set_light_state_in_scene(scene_id: SceneId, light_id: LightId, state: LightStateChange) -> CommandResponse
+            => |b| b.set_light_state_in_scene(&scene_id, light_id, &state);
+        fn delete_scene(id: SceneId) -> Vec<String> => |b| b.delete_scene(&id);
+        fn get_scene_with_states(id: SceneId) -> Scene => |b| b.get_scene_with_states(&id);
+
+        // SENSORS
+
+        fn get_all_sensors() -> BTreeMap<SensorId, Sensor> => |b| b.get_all_sensors();
+        fn get_sensor(id: SensorId) -> Sensor => |b| b.get_sensor(id);
+        fn create_sensor(sensor: Sensor) -> SensorId => |b| b.create_sensor(&sensor);
+        fn update_sensor(id: SensorId, name: String) -> CommandResponse => |b| b.update_sensor(id, name);
+        fn set_sensor_config(id: SensorId, config: SensorConfig) -> CommandResponse => |b| b.set_sensor_config(id, &config);
+        fn set_sensor_state(id: SensorId, state: SensorState) -> CommandResponse => |b| b.set_sensor_state(id, &state);
+        fn delete_sensor(id: SensorId) -> Vec<String> => |b| b.delete_sensor(id);
+
+        // RULES
+
+        fn get_all_rules() -> BTreeMap<RuleId, Rule> => |b| b.get_all_rules();
+        fn get_rule(id: RuleId) -> Rule => |b| b.get_rule(id);
+        fn create_rule(rule: RuleCreater) -> RuleId => |b| b.create_rule(&rule);
+        fn modify_rule(id: RuleId, rule: RuleModifier) -> CommandResponse => |b| b.modify_rule(id, &rule);
+        fn delete_rule(id: RuleId) -> Vec<String> => |b| b.delete_rule(id);
+
+        // SCHEDULES
+
+        fn get_all_schedules() -> BTreeMap<ScheduleId, Schedule> => |b| b.get_all_schedules();
+        fn get_schedule(id: ScheduleId) -> Schedule => |b| b.get_schedule(id);
+        fn create_schedule(schedule: ScheduleCreater) -> ScheduleId => |b| b.create_schedule(&schedule);
+        fn modify_schedule(id: ScheduleId, schedule: ScheduleModifier) -> CommandResponse
+            => |b| b.modify_schedule(id, &schedule);
+        fn delete_schedule(id: ScheduleId) -> Vec<String> => |b| b.delete_schedule(id);
+
+        // RESOURCELINKS
+
+        fn get_all_resourcelinks() -> BTreeMap<ResourceLinkId, ResourceLink> => |b| b.get_all_resourcelinks();
+        fn get_resourcelink(id: ResourceLinkId) -> ResourceLink => |b| b.get_resourcelink(id);
+        fn create_resourcelink(link: ResourceLinkCreater) -> ResourceLinkId => |b| b.create_resourcelink(&link);
+        fn modify_resourcelink(id: ResourceLinkId, link: ResourceLinkModifier) -> CommandResponse
+            => |b| b.modify_resourcelink(id, &link);
+        fn delete_resourcelink(id: ResourceLinkId) -> Vec<String> => |b| b.delete_resourcelink(id);
+    }
+}
+
+#[test]
+fn async_requests() {
+    use std::task::Wake;
+    use transport::{Method, Response};
+
+    struct Fake;
+    impl Transport for Fake {
+        fn send(&self, method: Method, path: &str, _: Option<&[u8]>) -> Result<Response> {
+            assert_eq!((method, path), (Method::Put, "/api/hello/groups/0/action"));
+            Ok(Response {
+                status: 200,
+                body: br#"[{"success":{"/groups/0/action/on":false}}]"#.to_vec(),
+            })
+        }
+    }
+
+    struct Unpark(thread::Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let bridge = AsyncBridge::from(Bridge::with_transport("test", "hello", Fake));
+    let mut future = bridge.set_group_state(GroupId::ALL, LightCommand::default().off());
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let resp = loop {
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(resp) => break resp.unwrap(),
+            Poll::Pending => thread::park(),
+        }
+    };
+    assert_eq!(resp.changes.len(), 1);
+}
+
+#[test]
+fn bounded_pool() {
+    use std::sync::mpsc;
+
+    let pool = Pool::new(2);
+    let running = Arc::new(Mutex::new((0, 0)));
+    let (done, finished) = mpsc::channel();
+    for _ in 0..6 {
+        let (running, done) = (running.clone(), done.clone());
+        pool.execute(Box::new(move || {
+            {
+                let mut r = running.lock().unwrap();
+                r.0 += 1;
+                r.1 = r.1.max(r.0);
+            }
+            thread::sleep(Duration::from_millis(20));
+            running.lock().unwrap().0 -= 1;
+            done.send(()).unwrap();
+        }));
+    }
+    for _ in 0..6 {
+        finished.recv().unwrap();
+    }
+    assert_eq!(running.lock().unwrap().1, 2);
+    assert!(pool.state.lock().unwrap().threads <= 2);
+}
@@ -1,12 +1,17 @@
+#[cfg(feature = "nupnp")]
 use hyper::Client;
-use hyper::client::Body;
 
-use std::io::Read;
 use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use serde_json::{to_vec, from_reader};
 
-use errors::{Result, HueError};
+use errors::{Result, HueErrorKind, BridgeError};
+use rate_limit::RateLimiter;
+use transport::{Transport, HyperTransport, Method, Response, Timeouts, Retrying, RetryPolicy};
+#[cfg(feature = "https")]
+use transport::CertificatePin;
 use ::hue::*;
 use ::json::*;
 
@@ -15,6 +20,7 @@ use ::json::*;
 pub fn discover() -> Result<Vec<Discovery>> {
     use hyper::net::HttpsConnector;
     use hyper_openssl::OpensslClient;
+    use errors::HueError;
 
     let ssl = OpensslClient::new().unwrap();
     let connector = HttpsConnector::new(ssl);
@@ -82,55 +88,65 @@ pub fn discover_upnp() -> ::std::result::Result<Vec<String>, ::ssdp::SSDPError>
 ///     }
 /// }
 /// ```
-pub fn register_user(ip: &str, devicetype: &str) -> Result<String> {
-    let client = Client::new();
-
+pub fn register_user(ip: &str, devicetype: &str) -> Result<Username> {
+    register_user_with_transport(&HyperTransport::new(ip), devicetype)
+}
+/// Same as `register_user()`, but sends the request through `transport`, which knows how to reach the bridge
+pub fn register_user_with_transport<T: Transport + ?Sized>(transport: &T, devicetype: &str) -> Result<Username> {
     let body = format!("{{\"devicetype\": {:?}}}", devicetype);
-    let body = body.as_bytes();
-    let url = format!("http://{}/api", ip);
-    let mut resp = client.post(&url)
-        .body(Body::BufBody(body, body.len()))
-        .send()?;
-
-    from_reader::<_, Vec<HueResponse<User>>>(&mut resp)?
-        .pop()
-        .unwrap()
-        .into_result()
-        .map(|u| u.username)
+
+    send_with_body::<_, User>(transport, Method::Post, "/api", body.as_bytes()).map(|u| u.username)
+}
+/// Same as `register_user()`, but over HTTPS, only accepting the certificate of the bridge with the id `bridgeid`
+/// that `pin` accepts.
+///
+/// This is usually the first connection to the bridge, so give it `CertificatePin::trust_on_first_use()`
+/// and save its fingerprint afterwards. See `HyperTransport::pinned()`.
+#[cfg(feature = "https")]
+pub fn register_user_https(ip: &str, devicetype: &str, bridgeid: &str, pin: &CertificatePin) -> Result<Username> {
+    register_user_with_transport(&HyperTransport::pinned(ip, bridgeid, pin.clone())?, devicetype)
 }
 
 #[derive(Debug)]
-/// The bridge connection
-pub struct Bridge {
-    client: Client,
-    url: String,
+/// The bridge connection.
+///
+/// Requests are sent through a `Transport`, which is a hyper `Client` by default.
+pub struct Bridge<T: Transport = HyperTransport> {
+    transport: T,
+    ip: String,
+    path: String,
+    check_capacity: bool,
+    validate_commands: bool,
+    limiter: Option<RateLimiter>,
 }
 
-fn send_with_body<'a, T>(rb: RequestBuilder<'a>, body: &'a [u8]) -> Result<T>
-    where for<'de> T: Deserialize<'de>
+fn send_with_body<T, R>(transport: &T, method: Method, path: &str, body: &[u8]) -> Result<R>
+    where T: Transport + ?Sized,
+          for<'de> R: Deserialize<'de>
 {
-    send(rb.body(Body::BufBody(body, body.len())))
+    transport.send(method, path, Some(body)).and_then(parse_response)
 }
 
-fn send<T>(rb: RequestBuilder) -> Result<T>
-    where for<'de> T: Deserialize<'de>
+fn send<T, R>(transport: &T, method: Method, path: &str) -> Result<R>
+    where T: Transport + ?Sized,
+          for<'de> R: Deserialize<'de>
 {
-    rb.send()
-        .map_err(HueError::from)
-        .and_then(|ref mut resp| {
-            let mut buf = Vec::new();
-            resp.read_to_end(&mut buf)?;
-            
-
-            match from_reader::<_, T>(&mut &*buf) {
-                Ok(t) => Ok(t),
-                Err(_) => from_reader::<_, Vec<HueResponse<T>>>(&mut &*buf)?
-                    .into_iter()
-                    .next()
-                    .ok_or_else(|| "Malformed response".into())
-                    .and_then(HueResponse::into_result)
-            }
-        })
+    transport.send(method, path, None).and_then(parse_response)
+}
+
+fn parse_response<R>(resp: Response) -> Result<R>
+    where for<'de> R: Deserialize<'de>
+{
+    let buf = resp.body;
+    match from_reader::<_, R>(&mut &*buf) {
+        Ok(t) => Ok(t),
+        Err(_) if resp.status >= 400 => Err(format!("Bridge responded with HTTP status {}", resp.status).into()),
+        Err(_) => from_reader::<_, Vec<HueResponse<R>>>(&mut &*buf)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Malformed response".into())
+            .and_then(HueResponse::into_result)
+    }
 }
 
 #[test]
@@ -140,12 +156,68 @@ fn get_ip_and_username() {
     assert_eq!(b.get_username(), "hello");
 }
 
-/// Many commands on the bridge return an array of things that were succesful.
-/// This is a type alias for that type.
-pub type SuccessVec = Vec<JsonMap<String, JsonValue>>;
+#[test]
+fn custom_transport() {
+    use std::cell::RefCell;
+
+    type Request = (Method, String, Option<Vec<u8>>);
+    struct Fake(RefCell<Vec<Request>>);
+    impl Transport for Fake {
+        fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+            self.0.borrow_mut().push((method, path.to_owned(), body.map(|b| b.to_vec())));
+            Ok(Response {
+                status: 200,
+                body: br#"[{"success":{"/lights/1/state/on":true}}]"#.to_vec(),
+            })
+        }
+    }
+
+    let b = Bridge::with_transport("test", "hello", Fake(RefCell::new(Vec::new())));
+    let resp = b.set_light_state(LightId::from(1), &LightCommand::default().on()).unwrap();
+    assert_eq!(resp.changes.get("/lights/1/state/on"), Some(&JsonValue::Bool(true)));
+
+    let requests = b.transport().0.borrow();
+    assert_eq!(requests[0].0, Method::Put);
+    assert_eq!(requests[0].1, "/api/hello/lights/1/state");
+    assert_eq!(requests[0].2.as_ref().map(|b| &b[..]), Some(&br#"{"on":true}"#[..]));
+}
+
+#[test]
+fn partial_failures() {
+    struct Fake(&'static [u8]);
+    impl Transport for Fake {
+        fn send(&self, _: Method, _: &str, _: Option<&[u8]>) -> Result<Response> {
+            Ok(Response { status: 200, body: self.0.to_vec() })
+        }
+    }
+
+    let command = LightCommand::default().on().with_bri(100).with_ct(300).with_transitiontime(Duration::from_secs(1));
+    let b = Bridge::with_transport("test", "hello", Fake(br#"[{"success":{"/lights/1/state/on":true}},
+        {"success":{"/lights/1/state/bri":100}},
+        {"error":{"type":6,"address":"/lights/1/state/ct","description":"parameter, ct, not available"}}]"#));
+    let resp = b.set_light_state(LightId::from(1), &command).unwrap();
+    assert!(!resp.is_complete());
+    assert_eq!(resp.changes.len(), 2);
+    assert_eq!(resp.errors[0].error, BridgeError::ParameterNotAvailable);
+    assert_eq!(resp.failed_attributes(), ["ct"]);
+    let retry = command.only(&resp.failed_attributes());
+    assert_eq!(to_vec(&retry).unwrap(), br#"{"ct":300,"transitiontime":10}"#);
+    assert!(resp.into_result().is_err());
+
+    let b = Bridge::with_transport("test", "hello", Fake(br#"[{"error":{"type":201,"address":"/lights/1/state/bri",
+        "description":"parameter, bri, is not modifiable. Device is set to off."}}]"#));
+    let resp = b.set_light_state(LightId::from(1), &LightCommand::default().with_bri(100)).unwrap();
+    assert!(resp.changes.is_empty());
+    assert_eq!(resp.errors[0].error, BridgeError::DeviceIsSetToOff);
+    assert!(resp.into_result().is_err());
+
+    // Errors about the whole light aren't attributes to retry
+    let b = Bridge::with_transport("test", "hello", Fake(br#"[{"error":{"type":3,"address":"/lights/1",
+        "description":"resource, /lights/1, not available"}}]"#));
+    assert!(b.set_light_state(LightId::from(1), &command).unwrap().failed_attributes().is_empty());
+}
 
 use serde::Deserialize;
-use hyper::client::RequestBuilder;
 
 fn extract<'de, T>(responses: Vec<HueResponse<T>>) -> Result<Vec<T>>
     where T: Deserialize<'de>
@@ -157,69 +229,207 @@ fn extract<'de, T>(responses: Vec<HueResponse<T>>) -> Result<Vec<T>>
     Ok(res_v)
 }
 
+/// Splits the responses to a command into the attributes that were changed and those that weren't
+fn command_response(responses: Vec<HueResponse<JsonMap<String, JsonValue>>>) -> Result<CommandResponse> {
+    let mut successes = Vec::new();
+    let mut errors = Vec::new();
+    for response in responses {
+        match (response.success, response.error) {
+            (Some(success), _) => successes.push(success),
+            (None, Some(e)) => errors.push(AttributeError {
+                path: ResourcePath::from_address(&e.address),
+                error: BridgeError::from(e.code),
+                description: e.description,
+            }),
+            (None, None) => return Err("Malformed response".into()),
+        }
+    }
+    Ok(CommandResponse {
+        changes: ChangeSet::from_successes(successes),
+        errors,
+    })
+}
+
 impl Bridge {
     /// Creates a `Bridge` on the given IP with the given username
-    pub fn new<S: Into<String>, U: Into<String>>(ip: S, username: U) -> Self {
+    pub fn new<S: Into<String>, U: Into<Username>>(ip: S, username: U) -> Self {
+        let ip = ip.into();
+        Bridge::with_transport(ip.clone(), username, HyperTransport::new(ip))
+    }
+    /// Creates a `Bridge` on the given IP with the given username, that gives up on requests after the timeouts
+    pub fn with_timeouts<S: Into<String>, U: Into<Username>>(ip: S, username: U, timeouts: Timeouts) -> Self {
+        let ip = ip.into();
+        Bridge::with_transport(ip.clone(), username, HyperTransport::with_timeouts(ip, timeouts))
+    }
+    /// Creates a `Bridge` on the given IP with the given username, that talks HTTPS to the bridge.
+    ///
+    /// Only a certificate with the id `bridgeid` as its common name that `pin` accepts is used,
+    /// see `HyperTransport::pinned()`. The id can be found in `Discovery::id` or `Configuration::bridgeid`.
+    #[cfg(feature = "https")]
+    pub fn new_https<S: Into<String>, U: Into<Username>>(ip: S, username: U, bridgeid: &str, pin: CertificatePin)
+                                                         -> Result<Self> {
+        let ip = ip.into();
+        Ok(Bridge::with_transport(ip.clone(), username, HyperTransport::pinned(ip, bridgeid, pin)?))
+    }
+}
+
+impl<T: Transport> Bridge<T> {
+    /// Creates a `Bridge` with the given username, sending requests through `transport`.
+    ///
+    /// The transport decides where the requests go, `ip` is only what `get_ip()` returns.
+    pub fn with_transport<S: Into<String>, U: Into<Username>>(ip: S, username: U, transport: T) -> Self {
+        Bridge {
+            transport,
+            ip: ip.into(),
+            path: format!("/api/{}/", username.into()),
+            check_capacity: false,
+            validate_commands: false,
+            limiter: None,
+        }
+    }
+    /// The transport requests are sent through
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+    /// Makes the `Bridge` send requests again when they fail with an error that might go away.
+    ///
+    /// See `Retrying` for which requests are retried.
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Bridge<Retrying<T>> {
+        let Bridge { transport, ip, path, check_capacity, validate_commands, limiter } = self;
         Bridge {
-            client: Client::new(),
-            url: format!("http://{}/api/{}/", ip.into(), username.into()),
+            transport: Retrying::new(transport, policy),
+            ip,
+            path,
+            check_capacity,
+            validate_commands,
+            limiter,
+        }
+    }
+    /// Makes the `Bridge` validate every `LightCommand` before sending it.
+    ///
+    /// Invalid commands make `set_light_state()` and `set_group_state()` fail with
+    /// `HueErrorKind::InvalidLightCommand` without sending anything. See `LightCommand::validate()`.
+    pub fn with_validation(self, validate: bool) -> Self {
+        Bridge { validate_commands: validate, ..self }
+    }
+    /// Makes the `Bridge` limit how fast it sends commands to lights and groups.
+    ///
+    /// This affects `set_light_state()`, `set_group_state()` and `recall_scene_in_group()`.
+    /// What happens when too many commands are sent depends on the `LimitMode` of the limiter.
+    /// A `Dispatcher` limits the rate itself, so a bridge used by one shouldn't have a limiter too.
+    pub fn with_rate_limit(self, limiter: RateLimiter) -> Self {
+        Bridge { limiter: Some(limiter), ..self }
+    }
+    /// Makes the `Bridge` check the capabilities of the bridge before creating anything.
+    ///
+    /// When there is no room for the new resource, the create methods fail with
+    /// `HueErrorKind::NoCapacity` without sending the create request. This costs an extra request per create.
+    pub fn with_capacity_check(self, check: bool) -> Self {
+        Bridge { check_capacity: check, ..self }
+    }
+    /// Fails if capacity checks are enabled and `has_room` says there is no room
+    fn ensure_capacity<F>(&self, address: &str, has_room: F) -> Result<()>
+        where F: FnOnce(&Capabilities) -> bool
+    {
+        if self.check_capacity && !has_room(&self.get_capabilities()?) {
+            Err(HueErrorKind::NoCapacity(address.to_owned()).into())
+        } else {
+            Ok(())
         }
     }
     /// Gets the IP of bridge
     pub fn get_ip(&self) -> &str {
-        self.url.split('/').nth(2).unwrap()
+        &self.ip
     }
     /// Gets the username this `Bridge` uses
     pub fn get_username(&self) -> &str {
-        self.url.split('/').nth(4).unwrap()
+        self.path.split('/').nth(2).unwrap()
     }
     /// Gets all lights that are connected to the bridge
-    pub fn get_all_lights(&self) -> Result<BTreeMap<usize, Light>> {
-        send(self.client.get(&format!("{}lights", self.url)))
+    pub fn get_all_lights(&self) -> Result<BTreeMap<LightId, Light>> {
+        send(&self.transport, Method::Get, &format!("{}lights", self.path))
     }
     /// Gets the light with the specific id
-    pub fn get_light(&self, id: usize) -> Result<Light> {
-        send(self.client.get(&format!("{}lights/{}", self.url, id)))
+    pub fn get_light(&self, id: LightId) -> Result<Light> {
+        send(&self.transport, Method::Get, &format!("{}lights/{}", self.path, id))
     }
-    /// Gets all the light that were found last time a search for new lights was done
-    pub fn get_new_lights(&self) -> Result<BTreeMap<usize, Light>> {
-        // TODO return lastscan too
-        send(self.client.get(&format!("{}lights/new", self.url)))
+    /// Gets all the light that were found last time a search for new lights was done,
+    /// together with the status of that search
+    pub fn get_new_lights(&self) -> Result<NewLights> {
+        send(&self.transport, Method::Get, &format!("{}lights/new", self.path))
     }
     /// Makes the bridge search for new lights (and switches).
     ///
-    /// The found lights can be retrieved with `get_new_lights()`
-    pub fn search_for_new_lights(&self) -> Result<SuccessVec> {
-        // TODO Allow deviceids to be specified
-        send(self.client.post(&format!("{}lights", self.url))).and_then(extract)
+    /// Up to 10 Zigbee device IDs (the serial numbers printed on the lights) can be given
+    /// to search for lights that are already paired with another bridge.
+    /// With no device IDs, the bridge searches for any new light.
+    /// More than 10 device IDs fail with `HueErrorKind::TooManyDeviceIds` without sending anything.
+    ///
+    /// The found lights can be retrieved with `get_new_lights()` or `wait_for_new_lights()`
+    pub fn search_for_new_lights(&self, deviceids: &[&str]) -> Result<CommandResponse> {
+        let path = format!("{}lights", self.path);
+        if deviceids.is_empty() {
+            return send(&self.transport, Method::Post, &path).and_then(command_response);
+        }
+        if deviceids.len() > 10 {
+            return Err(HueErrorKind::TooManyDeviceIds(deviceids.len()).into());
+        }
+        let mut body = BTreeMap::new();
+        body.insert("deviceid", deviceids);
+        send_with_body(&self.transport, Method::Post, &path, &to_vec(&body)?).and_then(command_response)
+    }
+    /// Waits for the current search for new lights to finish and returns the found lights.
+    ///
+    /// The bridge is polled every second. If the search is still active after `timeout`,
+    /// this fails with `HueErrorKind::SearchTimedOut`.
+    pub fn wait_for_new_lights(&self, timeout: Duration) -> Result<NewLights> {
+        let start = Instant::now();
+        loop {
+            let new_lights = self.get_new_lights()?;
+            if new_lights.lastscan != LastScan::Active {
+                return Ok(new_lights);
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(HueErrorKind::SearchTimedOut.into());
+            }
+            thread::sleep(::std::cmp::min(timeout - elapsed, Duration::from_secs(1)));
+        }
     }
     /// Sets the state of a light by sending a `LightCommand` to the bridge for this light
-    pub fn set_light_state(&self, id: usize, command: &LightCommand) -> Result<SuccessVec> {
-        send_with_body(self.client.put(&format!("{}lights/{}/state", self.url, id)),
+    pub fn set_light_state(&self, id: LightId, command: &LightCommand) -> Result<CommandResponse> {
+        if self.validate_commands {
+            command.validate()?;
+        }
+        if let Some(ref limiter) = self.limiter {
+            limiter.acquire_light(&format!("/lights/{}/state", id))?;
+        }
+        send_with_body(&self.transport, Method::Put, &format!("{}lights/{}/state", self.path, id),
                        &to_vec(command)?)
-            .and_then(extract)
+            .and_then(command_response)
     }
     /// Renames the light
-    pub fn rename_light(&self, id: usize, name: String) -> Result<SuccessVec> {
+    pub fn rename_light(&self, id: LightId, name: String) -> Result<CommandResponse> {
         let mut name_map = BTreeMap::new();
         name_map.insert("name".to_owned(), name);
-        send_with_body(self.client.put(&format!("{}lights/{}", self.url, id)),
+        send_with_body(&self.transport, Method::Put, &format!("{}lights/{}", self.path, id),
                        &to_vec(&name_map)?)
-            .and_then(extract)
+            .and_then(command_response)
     }
     /// Deletes a light from the bridge
-    pub fn delete_light(&self, id: usize) -> Result<SuccessVec> {
-        send(self.client.delete(&format!("{}lights/{}", self.url, id))).and_then(extract)
+    pub fn delete_light(&self, id: LightId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}lights/{}", self.path, id)).and_then(extract)
     }
 
     // GROUPS
 
     /// Gets all groups of the bridge
-    pub fn get_all_groups(&self) -> Result<BTreeMap<usize, Group>> {
-        send(self.client.get(&format!("{}groups", self.url)))
+    pub fn get_all_groups(&self) -> Result<BTreeMap<GroupId, Group>> {
+        send(&self.transport, Method::Get, &format!("{}groups", self.path))
     }
     /// Creates a group and returns the ID of the group
-    pub fn create_group(&self, name: String, lights: Vec<usize>, group_type: GroupType, room_class: Option<RoomClass>) -> Result<usize> {
+    pub fn create_group(&self, name: String, lights: Vec<LightId>, group_type: GroupType, room_class: Option<RoomClass>) -> Result<GroupId> {
+        self.ensure_capacity("/groups", |c| c.groups.available > 0)?;
         let g = Group {
             name: name,
             lights: lights,
@@ -228,100 +438,429 @@ impl Bridge {
             state: None,
             action: None,
         };
-        let r: HueResponse<Id<usize>> = send_with_body(self.client.post(&format!("{}groups", self.url)),
-                                                     &to_vec(&g)?)?;
-        r.into_result().map(|g| g.id)
+        let r: Id<GroupId> = send_with_body(&self.transport, Method::Post, &format!("{}groups", self.path),
+                                            &to_vec(&g)?)?;
+        Ok(r.id)
     }
     /// Gets extra information about a specific group
-    pub fn get_group_attributes(&self, id: usize) -> Result<Group> {
-        send(self.client.get(&format!("{}groups/{}", self.url, id)))
+    pub fn get_group_attributes(&self, id: GroupId) -> Result<Group> {
+        send(&self.transport, Method::Get, &format!("{}groups/{}", self.path, id))
     }
     /// Set the name, light and class of a group
-    pub fn set_group_attributes(&self, id: usize, attr: &GroupCommand) -> Result<SuccessVec> {
-        send_with_body(self.client.put(&format!("{}groups/{}", self.url, id)),
+    pub fn set_group_attributes(&self, id: GroupId, attr: &GroupCommand) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}groups/{}", self.path, id),
                        &to_vec(attr)?)
-            .and_then(extract)
+            .and_then(command_response)
     }
     /// Sets the state of all lights in the group.
     ///
-    /// ID 0 is a sepcial group containing all lights known to the bridge
-    pub fn set_group_state(&self, id: usize, state: &LightCommand) -> Result<SuccessVec> {
-        send_with_body(self.client.put(&format!("{}groups/{}/action", self.url, id)),
+    /// `GroupId::ALL` (ID 0) is a special group containing all lights known to the bridge
+    pub fn set_group_state(&self, id: GroupId, state: &LightCommand) -> Result<CommandResponse> {
+        if self.validate_commands {
+            state.validate()?;
+        }
+        if let Some(ref limiter) = self.limiter {
+            limiter.acquire_group(&format!("/groups/{}/action", id))?;
+        }
+        send_with_body(&self.transport, Method::Put, &format!("{}groups/{}/action", self.path, id),
                        &to_vec(state)?)
-            .and_then(extract)
+            .and_then(command_response)
     }
     /// Deletes the specified group
     ///
     /// It's not allowed to delete groups of type `LightSource` or `Luminaire`.
-    pub fn delete_group(&self, id: usize) -> Result<Vec<String>> {
-        send(self.client.delete(&format!("{}groups/{}", self.url, id))).and_then(extract)
+    pub fn delete_group(&self, id: GroupId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}groups/{}", self.path, id)).and_then(extract)
     }
 
     // CONFIGURATION
 
     /// Returns detailed information about the configuration of the bridge.
     pub fn get_configuration(&self) -> Result<Configuration> {
-        send(self.client.get(&format!("{}config", self.url)))
+        send(&self.transport, Method::Get, &format!("{}config", self.path))
     }
     /// Sets some configuration values.
-    pub fn modify_configuration(&self, command: &ConfigurationModifier) -> Result<SuccessVec> {
-        send_with_body(self.client.put(&format!("{}config", self.url)),
+    pub fn modify_configuration(&self, command: &ConfigurationModifier) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}config", self.path),
                        &to_vec(command)?)
-            .and_then(extract)
+            .and_then(command_response)
     }
     /// Deletes the specified user removing them from the whitelist.
-    pub fn delete_user(&self, username: &str) -> Result<Vec<String>> {
-        send(self.client.delete(&format!("{}config/whitelist/{}", self.url, username)))
+    pub fn delete_user(&self, username: &Username) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}config/whitelist/{}", self.path, username))
             .and_then(extract)
     }
+    /// Gets how many more resources of every kind can be created on the bridge
+    pub fn get_capabilities(&self) -> Result<Capabilities> {
+        send(&self.transport, Method::Get, &format!("{}capabilities", self.path))
+    }
     /// Fetches the entire datastore from the bridge.
     ///
     /// This is a resource intensive command for the bridge, and should therefore be used sparingly.
     pub fn get_full_state(&self) -> Result<FullState> {
-        send(self.client.get(&self.url))
+        send(&self.transport, Method::Get, &self.path)
     }
 
     /// Sets the state of lights in the group to the state in the scene
     ///
     /// Note that this will affect that are both in the group and in the scene.
-    /// Using group 0 will set all the lights in the scene, since group 0 is a special
+    /// Using `GroupId::ALL` will set all the lights in the scene, since group 0 is a special
     /// group that contains all lights
-    pub fn recall_scene_in_group(&self, group_id: usize, scene_id: &str) -> Result<SuccessVec> {
-        send_with_body(self.client.put(&format!("{}groups/{}/action", self.url, group_id)),
+    pub fn recall_scene_in_group(&self, group_id: GroupId, scene_id: &SceneId) -> Result<CommandResponse> {
+        if let Some(ref limiter) = self.limiter {
+            limiter.acquire_group(&format!("/groups/{}/action", group_id))?;
+        }
+        send_with_body(&self.transport, Method::Put, &format!("{}groups/{}/action", self.path, group_id),
                        &to_vec(&SceneRecall{scene: scene_id})?)
-            .and_then(extract)
+            .and_then(command_response)
     }
 
     // SCENES
 
     /// Gets all scenes of the bridge
-    pub fn get_all_scenes(&self) -> Result<BTreeMap<String, Scene>> {
-        send(self.client.get(&format!("{}scenes", self.url)))
+    pub fn get_all_scenes(&self) -> Result<BTreeMap<SceneId, Scene>> {
+        send(&self.transport, Method::Get, &format!("{}scenes", self.path))
     }
     /// Creates a scene on the bridge and returns the ID of the created scene.
-    pub fn create_scene(&self, scene: &SceneCreater) -> Result<String> {
-        let r: HueResponse<Id<String>> = send_with_body(self.client.post(&format!("{}scenes", self.url)),
-                                                        &to_vec(scene)?)?;
-        r.into_result().map(|g| g.id)
+    pub fn create_scene(&self, scene: &SceneCreater) -> Result<SceneId> {
+        self.ensure_capacity("/scenes", |c| {
+            c.scenes.available > 0 && c.scenes.lightstates.available >= scene.lights.len()
+        })?;
+        let r: Id<SceneId> = send_with_body(&self.transport, Method::Post, &format!("{}scenes", self.path),
+                                            &to_vec(scene)?)?;
+        Ok(r.id)
     }
     /// Sets general things in the specified scene
-    pub fn modify_scene(&self, id: &str, scene: &SceneModifier) -> Result<SuccessVec> {
-        send_with_body(self.client.put(&format!("{}scenes/{}", self.url, id)), &to_vec(scene)?)
-            .and_then(extract)
+    pub fn modify_scene(&self, id: &SceneId, scene: &SceneModifier) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}scenes/{}", self.path, id), &to_vec(scene)?)
+            .and_then(command_response)
     }
     /// Sets the light state of the specified ID that is stored in the scene
-    pub fn set_light_state_in_scene(&self, scene_id: &str, light_id: usize,
-        state: &LightStateChange) -> Result<SuccessVec> {
+    pub fn set_light_state_in_scene(&self, scene_id: &SceneId, light_id: LightId,
+        state: &LightStateChange) -> Result<CommandResponse> {
 
-        send_with_body(self.client.put(&format!("{}scenes/{}/lightstates/{}", self.url,
-            scene_id, light_id)), &to_vec(state)?).and_then(extract)
+        send_with_body(&self.transport, Method::Put, &format!("{}scenes/{}/lightstates/{}", self.path,
+            scene_id, light_id), &to_vec(state)?).and_then(command_response)
     }
     /// Deletes the specified scene
-    pub fn delete_scene(&self, id: &str) -> Result<Vec<String>> {
-        send(self.client.delete(&format!("{}scenes/{}", self.url, id))).and_then(extract)
+    pub fn delete_scene(&self, id: &SceneId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}scenes/{}", self.path, id)).and_then(extract)
     }
     /// Gets the scene with the specified ID with its `lightstates`
-    pub fn get_scene_with_states(&self, id: &str) -> Result<Scene> {
-        send(self.client.get(&format!("{}scenes/{}", self.url, id)))
+    pub fn get_scene_with_states(&self, id: &SceneId) -> Result<Scene> {
+        send(&self.transport, Method::Get, &format!("{}scenes/{}", self.path, id))
     }
+
+    // SENSORS
+
+    /// Gets all sensors of the bridge
+    pub fn get_all_sensors(&self) -> Result<BTreeMap<SensorId, Sensor>> {
+        send(&self.transport, Method::Get, &format!("{}sensors", self.path))
+    }
+    /// Gets the sensor with the specific id
+    pub fn get_sensor(&self, id: SensorId) -> Result<Sensor> {
+        send(&self.transport, Method::Get, &format!("{}sensors/{}", self.path, id))
+    }
+    /// Creates a sensor on the bridge and returns the ID of the created sensor.
+    ///
+    /// Only CLIP sensors can be created this way.
+    pub fn create_sensor(&self, sensor: &Sensor) -> Result<SensorId> {
+        self.ensure_capacity("/sensors", |c| {
+            c.sensors.available > 0 && c.sensors.clip.map(|clip| clip.available > 0).unwrap_or(true)
+        })?;
+        let r: Id<SensorId> = send_with_body(&self.transport, Method::Post, &format!("{}sensors", self.path),
+                                             &to_vec(sensor)?)?;
+        Ok(r.id)
+    }
+    /// Renames the sensor
+    pub fn update_sensor(&self, id: SensorId, name: String) -> Result<CommandResponse> {
+        let mut name_map = BTreeMap::new();
+        name_map.insert("name".to_owned(), name);
+        send_with_body(&self.transport, Method::Put, &format!("{}sensors/{}", self.path, id),
+                       &to_vec(&name_map)?)
+            .and_then(command_response)
+    }
+    /// Changes the configuration of the sensor. Only the fields that are set will be changed.
+    pub fn set_sensor_config(&self, id: SensorId, config: &SensorConfig) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}sensors/{}/config", self.path, id),
+                       &to_vec(config)?)
+            .and_then(command_response)
+    }
+    /// Changes the state of the sensor. Only the fields that are set will be changed.
+    ///
+    /// This is only allowed for CLIP sensors.
+    pub fn set_sensor_state(&self, id: SensorId, state: &SensorState) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}sensors/{}/state", self.path, id),
+                       &to_vec(state)?)
+            .and_then(command_response)
+    }
+    /// Deletes the specified sensor
+    pub fn delete_sensor(&self, id: SensorId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}sensors/{}", self.path, id)).and_then(extract)
+    }
+
+    // RULES
+
+    /// Gets all rules of the bridge
+    pub fn get_all_rules(&self) -> Result<BTreeMap<RuleId, Rule>> {
+        send(&self.transport, Method::Get, &format!("{}rules", self.path))
+    }
+    /// Gets the rule with the specific id
+    pub fn get_rule(&self, id: RuleId) -> Result<Rule> {
+        send(&self.transport, Method::Get, &format!("{}rules/{}", self.path, id))
+    }
+    /// Creates a rule on the bridge and returns the ID of the created rule.
+    pub fn create_rule(&self, rule: &RuleCreater) -> Result<RuleId> {
+        self.ensure_capacity("/rules", |c| {
+            c.rules.available > 0 && c.rules.conditions.available >= rule.conditions.len() &&
+            c.rules.actions.available >= rule.actions.len()
+        })?;
+        let r: Id<RuleId> = send_with_body(&self.transport, Method::Post, &format!("{}rules", self.path),
+                                           &to_vec(rule)?)?;
+        Ok(r.id)
+    }
+    /// Sets the name, conditions, actions or status of the specified rule
+    pub fn modify_rule(&self, id: RuleId, rule: &RuleModifier) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}rules/{}", self.path, id), &to_vec(rule)?)
+            .and_then(command_response)
+    }
+    /// Deletes the specified rule
+    pub fn delete_rule(&self, id: RuleId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}rules/{}", self.path, id)).and_then(extract)
+    }
+
+    // SCHEDULES
+
+    /// Gets all schedules of the bridge
+    pub fn get_all_schedules(&self) -> Result<BTreeMap<ScheduleId, Schedule>> {
+        send(&self.transport, Method::Get, &format!("{}schedules", self.path))
+    }
+    /// Gets the schedule with the specific id
+    pub fn get_schedule(&self, id: ScheduleId) -> Result<Schedule> {
+        send(&self.transport, Method::Get, &format!("{}schedules/{}", self.path, id))
+    }
+    /// Creates a schedule on the bridge and returns the ID of the created schedule.
+    pub fn create_schedule(&self, schedule: &ScheduleCreater) -> Result<ScheduleId> {
+        self.ensure_capacity("/schedules", |c| c.schedules.available > 0)?;
+        let r: Id<ScheduleId> = send_with_body(&self.transport, Method::Post, &format!("{}schedules", self.path),
+                                               &to_vec(schedule)?)?;
+        Ok(r.id)
+    }
+    /// Sets the name, description, command, time or status of the specified schedule
+    pub fn modify_schedule(&self, id: ScheduleId, schedule: &ScheduleModifier) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}schedules/{}", self.path, id), &to_vec(schedule)?)
+            .and_then(command_response)
+    }
+    /// Deletes the specified schedule
+    pub fn delete_schedule(&self, id: ScheduleId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}schedules/{}", self.path, id)).and_then(extract)
+    }
+
+    // RESOURCELINKS
+
+    /// Gets all resourcelinks of the bridge
+    pub fn get_all_resourcelinks(&self) -> Result<BTreeMap<ResourceLinkId, ResourceLink>> {
+        send(&self.transport, Method::Get, &format!("{}resourcelinks", self.path))
+    }
+    /// Gets the resourcelink with the specific id
+    pub fn get_resourcelink(&self, id: ResourceLinkId) -> Result<ResourceLink> {
+        send(&self.transport, Method::Get, &format!("{}resourcelinks/{}", self.path, id))
+    }
+    /// Creates a resourcelink on the bridge and returns the ID of the created resourcelink.
+    pub fn create_resourcelink(&self, link: &ResourceLinkCreater) -> Result<ResourceLinkId> {
+        self.ensure_capacity("/resourcelinks", |c| c.resourcelinks.available > 0)?;
+        let r: Id<ResourceLinkId> = send_with_body(&self.transport, Method::Post, &format!("{}resourcelinks", self.path),
+                                                   &to_vec(link)?)?;
+        Ok(r.id)
+    }
+    /// Sets the name, description, class or links of the specified resourcelink
+    pub fn modify_resourcelink(&self, id: ResourceLinkId, link: &ResourceLinkModifier) -> Result<CommandResponse> {
+        send_with_body(&self.transport, Method::Put, &format!("{}resourcelinks/{}", self.path, id), &to_vec(link)?)
+            .and_then(command_response)
+    }
+    /// Deletes the specified resourcelink
+    ///
+    /// The linked resources themselves are not deleted.
+    pub fn delete_resourcelink(&self, id: ResourceLinkId) -> Result<Vec<String>> {
+        send(&self.transport, Method::Delete, &format!("{}resourcelinks/{}", self.path, id)).and_then(extract)
+    }
+}
+
+#[test]
+fn create_methods() {
+    use errors::HueError;
+    use serde_json::from_str;
+
+    // Answers like the bridge does when something was created
+    struct Created(&'static str);
+    impl Transport for Created {
+        fn send(&self, _: Method, _: &str, _: Option<&[u8]>) -> Result<Response> {
+            Ok(Response {
+                status: 200,
+                body: format!(r#"[{{"success":{{"id":"{}"}}}}]"#, self.0).into_bytes(),
+            })
+        }
+    }
+    let bridge = |id| Bridge::with_transport("test", "hello", Created(id));
+
+    let group = bridge("4").create_group("Kitchen".to_owned(), vec![LightId::from(1)], GroupType::Room, None);
+    assert_eq!(group.unwrap(), GroupId::from(4));
+
+    let scene = SceneCreater {
+        name: "Evening".to_owned(),
+        lights: vec![LightId::from(1)],
+        recycle: None,
+        appdata: None,
+        picture: None,
+        transitiontime: None,
+    };
+    assert_eq!(bridge("ab1f5a2").create_scene(&scene).unwrap(), SceneId::from("ab1f5a2"));
+
+    let sensor: Sensor = from_str(r#"{"name":"Away","type":"CLIPGenericFlag","modelid":"flag",
+                                      "manufacturername":"philipshue","swversion":"1.0"}"#).unwrap();
+    assert_eq!(bridge("5").create_sensor(&sensor).unwrap(), SensorId::from(5));
+
+    let action = Action::new("/groups/0/action", ActionMethod::Put, &LightCommand::default().off()).unwrap();
+    let rule = RuleCreater {
+        name: "Away".to_owned(),
+        conditions: vec![Condition::changed("/sensors/5/state/flag")],
+        actions: vec![action.clone()],
+        status: None,
+        recycle: None,
+    };
+    assert_eq!(bridge("6").create_rule(&rule).unwrap(), RuleId::from(6));
+
+    let schedule = ScheduleCreater {
+        name: None,
+        description: None,
+        command: action,
+        localtime: "W124/T23:00:00".parse().unwrap(),
+        status: None,
+        autodelete: None,
+        recycle: None,
+    };
+    assert_eq!(bridge("7").create_schedule(&schedule).unwrap(), ScheduleId::from(7));
+
+    let link = ResourceLinkCreater {
+        name: "Away".to_owned(),
+        description: None,
+        classid: 1,
+        recycle: None,
+        links: vec!["/sensors/5".to_owned(), "/rules/6".to_owned()],
+    };
+    assert_eq!(bridge("8").create_resourcelink(&link).unwrap(), ResourceLinkId::from(8));
+
+    // The bridge's reason is kept when it refuses to create something
+    let failing = |reply| Bridge::with_transport("test", "hello", Canned::new(reply));
+    let failures = [
+        (failing(r#"[{"error":{"type":502,"address":"/sensors","description":"Sensor list is full"}}]"#)
+            .create_sensor(&sensor).map(drop), BridgeError::SensorListIsFull),
+        (failing(r#"[{"error":{"type":601,"address":"/rules","description":"Rule engine full"}}]"#)
+            .create_rule(&rule).map(drop), BridgeError::RuleEngineFull),
+        (failing(r#"[{"error":{"type":701,"address":"/schedules","description":"Schedule list is full"}}]"#)
+            .create_schedule(&schedule).map(drop), BridgeError::ScheduleListIsFull),
+        (failing(r#"[{"error":{"type":11,"address":"/resourcelinks","description":"Too many items in list"}}]"#)
+            .create_resourcelink(&link).map(drop), BridgeError::TooManyItemsInList),
+    ];
+    for &(ref result, expected) in failures.iter() {
+        match *result {
+            Err(HueError(HueErrorKind::BridgeError { error, .. }, _)) if error == expected => (),
+            ref other => panic!("expected {:?}, got {:?}", expected, other),
+        }
+    }
+}
+
+/// Answers requests with the given bodies in order, repeating the last one, keeping the requests
+#[cfg(test)]
+struct Canned {
+    replies: Vec<&'static str>,
+    requests: ::std::cell::RefCell<Vec<(Method, String, Option<JsonValue>)>>,
+}
+
+#[cfg(test)]
+impl Canned {
+    fn new(reply: &'static str) -> Self {
+        Canned::replies(vec![reply])
+    }
+    fn replies(replies: Vec<&'static str>) -> Self {
+        Canned {
+            replies,
+            requests: Default::default(),
+        }
+    }
+    /// The method, path and body of the only request sent
+    fn request(&self) -> (Method, String, Option<JsonValue>) {
+        let requests = self.requests.borrow();
+        assert_eq!(requests.len(), 1);
+        requests[0].clone()
+    }
+}
+
+#[cfg(test)]
+impl Transport for Canned {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        let body = body.map(|b| ::serde_json::from_slice(b).unwrap());
+        let mut requests = self.requests.borrow_mut();
+        requests.push((method, path.to_owned(), body));
+        let reply = self.replies[::std::cmp::min(requests.len(), self.replies.len()) - 1];
+        Ok(Response {
+            status: 200,
+            body: reply.as_bytes().to_vec(),
+        })
+    }
+}
+
+#[test]
+fn search_for_device_ids() {
+    use errors::HueError;
+
+    let bridge = Bridge::with_transport("test", "hello", Canned::new(r#"[{"success":{"/lights":"Searching for new devices"}}]"#));
+    bridge.search_for_new_lights(&["45AF34", "543636"]).unwrap();
+    let (method, path, body) = bridge.transport().request();
+    assert_eq!((method, &*path), (Method::Post, "/api/hello/lights"));
+    assert_eq!(body.unwrap(), ::serde_json::json!({"deviceid": ["45AF34", "543636"]}));
+
+    match bridge.search_for_new_lights(&["45AF34"; 11]) {
+        Err(HueError(HueErrorKind::TooManyDeviceIds(11), _)) => (),
+        other => panic!("expected TooManyDeviceIds, got {:?}", other),
+    }
+    assert_eq!(bridge.transport().requests.borrow().len(), 1);
+}
+
+#[test]
+fn capacity_check() {
+    use errors::HueError;
+
+    const CAPABILITIES: &str = r#"{
+        "lights": {"available": 10},
+        "sensors": {"available": 60, "clip": {"available": 0}, "zll": {"available": 60}, "zgp": {"available": 60}},
+        "groups": {"available": 60},
+        "scenes": {"available": 100, "lightstates": {"available": 2}},
+        "rules": {"available": 150, "conditions": {"available": 1500}, "actions": {"available": 1000}},
+        "schedules": {"available": 100},
+        "resourcelinks": {"available": 64}
+    }"#;
+    let scene = SceneCreater {
+        name: "Evening".to_owned(),
+        lights: vec![1.into(), 2.into(), 3.into()],
+        recycle: None,
+        appdata: None,
+        picture: None,
+        transitiontime: None,
+    };
+    let bridge = Bridge::with_transport("test", "hello", Canned::new(CAPABILITIES)).with_capacity_check(true);
+    match bridge.create_scene(&scene) {
+        Err(HueError(HueErrorKind::NoCapacity(ref address), _)) if address == "/scenes" => (),
+        other => panic!("expected NoCapacity, got {:?}", other),
+    }
+    let (method, path, _) = bridge.transport().request();
+    assert_eq!((method, &*path), (Method::Get, "/api/hello/capabilities"));
+
+    let bridge = Bridge::with_transport("test", "hello", Canned::replies(vec![CAPABILITIES, r#"[{"success":{"id":"4"}}]"#]))
+        .with_capacity_check(true);
+    assert_eq!(bridge.create_group("Hall".to_owned(), vec![1.into()], GroupType::LightGroup, None).unwrap(),
+               GroupId::from(4));
+    let requests = bridge.transport().requests.borrow();
+    assert_eq!(requests.iter().map(|r| (r.0, &*r.1)).collect::<Vec<_>>(),
+               vec![(Method::Get, "/api/hello/capabilities"), (Method::Post, "/api/hello/groups")]);
 }
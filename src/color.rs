@@ -0,0 +1,355 @@
+use errors::{Result, HueErrorKind};
+
+/// A point in CIE xy colour space
+pub type Xy = (f32, f32);
+
+/// The white point of sRGB (D65)
+pub const WHITE: Xy = (0.3127, 0.3290);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The triangle of colours in CIE xy space a light can show
+pub struct Gamut {
+    /// The most red colour the light can show
+    pub red: Xy,
+    /// The most green colour the light can show
+    pub green: Xy,
+    /// The most blue colour the light can show
+    pub blue: Xy,
+}
+
+impl Gamut {
+    /// Gamut of the LivingColors lights and the first LightStrips
+    pub const A: Gamut = Gamut {
+        red: (0.704, 0.296),
+        green: (0.2151, 0.7106),
+        blue: (0.138, 0.08),
+    };
+    /// Gamut of the first generations of Hue bulbs
+    pub const B: Gamut = Gamut {
+        red: (0.675, 0.322),
+        green: (0.409, 0.518),
+        blue: (0.167, 0.04),
+    };
+    /// Gamut of the newer Hue bulbs and LightStrips
+    pub const C: Gamut = Gamut {
+        red: (0.692, 0.308),
+        green: (0.17, 0.7),
+        blue: (0.153, 0.048),
+    };
+
+    /// The gamut of a light model (the `modelid` of a `Light`), if the model is known
+    pub fn for_model(modelid: &str) -> Option<Gamut> {
+        match modelid {
+            "LLC001" | "LLC005" | "LLC006" | "LLC007" | "LLC010" | "LLC011" | "LLC012" |
+            "LLC013" | "LLC014" | "LST001" => Some(Gamut::A),
+            "LCT001" | "LCT002" | "LCT003" | "LCT007" | "LLM001" => Some(Gamut::B),
+            "LCT010" | "LCT011" | "LCT012" | "LCT014" | "LCT015" | "LCT016" | "LLC020" |
+            "LST002" => Some(Gamut::C),
+            _ => None,
+        }
+    }
+    /// The gamut with the given name ("A", "B" or "C") as reported in the capabilities of a light
+    pub fn from_name(name: &str) -> Option<Gamut> {
+        match name {
+            "A" => Some(Gamut::A),
+            "B" => Some(Gamut::B),
+            "C" => Some(Gamut::C),
+            _ => None,
+        }
+    }
+    /// Whether the light can show the colour
+    pub fn contains(&self, xy: Xy) -> bool {
+        let side = |a: Xy, b: Xy| cross(sub(b, a), sub(xy, a));
+        let (r, g, b) = (side(self.red, self.green), side(self.green, self.blue), side(self.blue, self.red));
+        (r >= 0. && g >= 0. && b >= 0.) || (r <= 0. && g <= 0. && b <= 0.)
+    }
+    /// The closest colour to `xy` the light can show
+    pub fn clamp(&self, xy: Xy) -> Xy {
+        if self.contains(xy) {
+            return xy;
+        }
+        let candidates = [
+            closest_on_line(self.red, self.green, xy),
+            closest_on_line(self.green, self.blue, xy),
+            closest_on_line(self.blue, self.red, xy),
+        ];
+        let mut best = candidates[0];
+        for &c in candidates[1..].iter() {
+            if distance(c, xy) < distance(best, xy) {
+                best = c;
+            }
+        }
+        best
+    }
+}
+
+fn sub(a: Xy, b: Xy) -> Xy {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn cross(a: Xy, b: Xy) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn distance(a: Xy, b: Xy) -> f32 {
+    let d = sub(a, b);
+    (d.0 * d.0 + d.1 * d.1).sqrt()
+}
+
+fn closest_on_line(a: Xy, b: Xy, p: Xy) -> Xy {
+    let ab = sub(b, a);
+    let ap = sub(p, a);
+    let t = ((ap.0 * ab.0 + ap.1 * ab.1) / (ab.0 * ab.0 + ab.1 * ab.1)).clamp(0., 1.);
+    (a.0 + ab.0 * t, a.1 + ab.1 * t)
+}
+
+/// Removes the sRGB gamma from a component between 0 and 1
+fn linearize(c: f32) -> f32 {
+    if c > 0.04045 {
+        ((c + 0.055) / 1.055).powf(2.4)
+    } else {
+        c / 12.92
+    }
+}
+
+/// Converts an sRGB colour to the closest xy colour the light can show and a brightness.
+///
+/// The brightness is the brightness of the brightest component, between 1 and 254,
+/// so pure colours are shown at full brightness. Black is shown at the lowest brightness
+/// with a white colour; turn the light off to show black.
+pub fn rgb_to_xy(r: u8, g: u8, b: u8, gamut: &Gamut) -> (Xy, u8) {
+    let (lr, lg, lb) = (linearize(r as f32 / 255.), linearize(g as f32 / 255.), linearize(b as f32 / 255.));
+
+    let x = lr * 0.4124 + lg * 0.3576 + lb * 0.1805;
+    let y = lr * 0.2126 + lg * 0.7152 + lb * 0.0722;
+    let z = lr * 0.0193 + lg * 0.1192 + lb * 0.9505;
+    let sum = x + y + z;
+
+    let xy = if sum > 0. { (x / sum, y / sum) } else { WHITE };
+    let max = r.max(g).max(b) as f32 / 255.;
+    let bri = (max * 254.).round().max(1.) as u8;
+    (gamut.clamp(xy), bri)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A colour as the bridge reports it, in whichever representation is in effect
+pub enum Color {
+    /// Hue (0 to 65535) and saturation (0 to 254)
+    Hs {
+        /// Hue of the colour. Both 0 and 65535 are red, 25500 is green and 46920 is blue.
+        hue: u16,
+        /// Saturation of the colour. 254 is the most saturated and 0 is white.
+        sat: u8,
+    },
+    /// A point in CIE xy colour space
+    Xy(Xy),
+    /// A colour temperature in mired
+    Ct(u16),
+}
+
+impl Color {
+    /// Converts the colour shown at the brightness `bri` (1 to 254) to sRGB
+    pub fn to_rgb(&self, bri: u8) -> (u8, u8, u8) {
+        let (r, g, b) = match *self {
+            Color::Hs { hue, sat } => hs_to_rgb(hue, sat),
+            Color::Xy(xy) => xy_to_rgb(xy),
+            Color::Ct(ct) => ct_to_rgb(ct),
+        };
+        let scale = |c: f32| (c * bri.min(254) as f32 / 254. * 255.).round().clamp(0., 255.) as u8;
+        (scale(r), scale(g), scale(b))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A colour temperature. Higher mired values, or lower Kelvin values, are warmer.
+pub struct ColorTemperature(u16);
+
+impl ColorTemperature {
+    /// A colour temperature in [mired](http://en.wikipedia.org/wiki/Mired)
+    pub fn from_mired(mired: u16) -> Self {
+        ColorTemperature(mired)
+    }
+    /// A colour temperature in Kelvin, rounded to the nearest mired
+    pub fn from_kelvin(kelvin: u32) -> Self {
+        let mired = (1_000_000. / kelvin.max(1) as f64).round();
+        ColorTemperature(mired.min(u16::MAX as f64) as u16)
+    }
+    /// The colour temperature in mired
+    pub fn mired(&self) -> u16 {
+        self.0
+    }
+    /// The colour temperature in Kelvin, rounded to the nearest Kelvin
+    pub fn kelvin(&self) -> u32 {
+        (1_000_000. / self.0.max(1) as f64).round() as u32
+    }
+    /// The closest colour temperature within `range`. An empty range, with `min` above `max`, gives `max`.
+    pub fn clamp(&self, range: &CtRange) -> Self {
+        ColorTemperature(self.0.max(range.min).min(range.max))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+/// The colour temperatures a light can show, in mired
+pub struct CtRange {
+    /// The coldest colour temperature
+    pub min: u16,
+    /// The warmest colour temperature
+    pub max: u16,
+}
+
+impl CtRange {
+    /// The range the bridge accepts, from 6500 K to 2000 K
+    pub const FULL: CtRange = CtRange { min: 153, max: 500 };
+    /// Range of the white ambiance lights, from 6500 K to 2200 K
+    pub const AMBIANCE: CtRange = CtRange { min: 153, max: 454 };
+
+    /// The colour temperature range of a light model (the `modelid` of a `Light`), if the model is known
+    pub fn for_model(modelid: &str) -> Option<CtRange> {
+        match modelid {
+            "LCT001" | "LCT002" | "LCT003" | "LCT007" | "LLM001" | "LCT010" | "LCT011" |
+            "LCT012" | "LCT014" | "LCT015" | "LCT016" | "LLC020" | "LST002" => Some(CtRange::FULL),
+            "LTW001" | "LTW004" | "LTW010" | "LTW011" | "LTW012" | "LTW013" | "LTW014" |
+            "LTW015" | "LLM010" | "LLM011" | "LLM012" => Some(CtRange::AMBIANCE),
+            _ => None,
+        }
+    }
+}
+
+/// Adds the sRGB gamma to a linear component between 0 and 1
+fn gamma(c: f32) -> f32 {
+    if c > 0.0031308 {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    } else {
+        12.92 * c
+    }
+}
+
+fn hs_to_rgb(hue: u16, sat: u8) -> (f32, f32, f32) {
+    let h = hue as f32 / 65536. * 6.;
+    let s = sat.min(254) as f32 / 254.;
+    let f = h - h.floor();
+    let (p, q, t) = (1. - s, 1. - s * f, 1. - s * (1. - f));
+    match h as u8 {
+        0 => (1., t, p),
+        1 => (q, 1., p),
+        2 => (p, 1., t),
+        3 => (p, q, 1.),
+        4 => (t, p, 1.),
+        _ => (1., p, q),
+    }
+}
+
+fn xy_to_rgb(xy: Xy) -> (f32, f32, f32) {
+    let (x, y) = if xy.1 > 0. { xy } else { WHITE };
+    let (cx, cy, cz) = (x / y, 1., (1. - x - y) / y);
+
+    let r = cx * 3.2406 - cy * 1.5372 - cz * 0.4986;
+    let g = -cx * 0.9689 + cy * 1.8758 + cz * 0.0415;
+    let b = cx * 0.0557 - cy * 0.2040 + cz * 1.0570;
+    let (r, g, b) = (r.max(0.), g.max(0.), b.max(0.));
+
+    // Show the colour at full brightness; the brightness of the light is applied afterwards
+    let max = r.max(g).max(b);
+    if max > 0. {
+        (gamma(r / max), gamma(g / max), gamma(b / max))
+    } else {
+        (1., 1., 1.)
+    }
+}
+
+/// Approximates the colour of a black body at the temperature `ct` in mired
+fn ct_to_rgb(ct: u16) -> (f32, f32, f32) {
+    let temp = 10_000. / ct.max(1) as f32;
+    let r = if temp <= 66. {
+        255.
+    } else {
+        329.69873 * (temp - 60.).powf(-0.13320476)
+    };
+    let g = if temp <= 66. {
+        99.4708 * temp.ln() - 161.11957
+    } else {
+        288.12217 * (temp - 60.).powf(-0.07551485)
+    };
+    let b = if temp >= 66. {
+        255.
+    } else if temp <= 19. {
+        0.
+    } else {
+        138.51773 * (temp - 10.).ln() - 305.0448
+    };
+    let c = |c: f32| c.clamp(0., 255.) / 255.;
+    (c(r), c(g), c(b))
+}
+
+/// Parses a colour in hex notation such as "#ff8800", "ff8800" or "#f80"
+pub fn hex_to_rgb(hex: &str) -> Result<(u8, u8, u8)> {
+    let invalid = || HueErrorKind::InvalidColor(hex.to_owned()).into();
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let component = |s: &str| u8::from_str_radix(s, 16).map_err(|_| invalid());
+    match digits.len() {
+        6 => Ok((component(&digits[0..2])?, component(&digits[2..4])?, component(&digits[4..6])?)),
+        3 => {
+            let short = |i: usize| component(&digits[i..i + 1]).map(|c| c * 17);
+            Ok((short(0)?, short(1)?, short(2)?))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[test]
+fn rgb_conversion() {
+    let (xy, bri) = rgb_to_xy(255, 255, 255, &Gamut::C);
+    assert!(distance(xy, WHITE) < 0.001);
+    assert_eq!(bri, 254);
+
+    // sRGB green lies outside gamut B and is clamped onto it
+    let (xy, bri) = rgb_to_xy(0, 255, 0, &Gamut::B);
+    assert!(!Gamut::B.contains((0.3, 0.6)));
+    assert!(Gamut::B.contains(xy));
+    assert!(distance(xy, Gamut::B.green) < 0.05);
+    assert_eq!(bri, 254);
+    let (xy, _) = rgb_to_xy(0, 255, 0, &Gamut::C);
+    assert!(distance(xy, (0.3, 0.6)) < 0.001);
+
+    let (_, bri) = rgb_to_xy(0, 0, 0, &Gamut::A);
+    assert_eq!(bri, 1);
+
+    assert_eq!(hex_to_rgb("#ff8800").unwrap(), (255, 136, 0));
+    assert_eq!(hex_to_rgb("f80").unwrap(), (255, 136, 0));
+    assert!(hex_to_rgb("#ff88").is_err());
+    assert!(hex_to_rgb("#gg8800").is_err());
+}
+
+#[test]
+fn color_to_rgb() {
+    assert_eq!(Color::Hs { hue: 0, sat: 254 }.to_rgb(254), (255, 0, 0));
+    assert_eq!(Color::Hs { hue: 46920, sat: 0 }.to_rgb(127), (128, 128, 128));
+    assert_eq!(Color::Xy(WHITE).to_rgb(254), (255, 255, 255));
+
+    // Converting a colour inside the gamut and back gives the same colour
+    let (xy, bri) = rgb_to_xy(200, 100, 50, &Gamut::C);
+    let (r, g, b) = Color::Xy(xy).to_rgb(bri);
+    assert!((r as i32 - 200).abs() <= 2 && (g as i32 - 100).abs() <= 2 && (b as i32 - 50).abs() <= 2);
+
+    // Warm white is orange-ish, daylight is close to white
+    let (r, g, b) = Color::Ct(500).to_rgb(254);
+    assert!(r > g && g > b);
+    let (r, g, b) = Color::Ct(153).to_rgb(254);
+    assert!(r > 240 && g > 240 && b > 240);
+}
+
+#[test]
+fn color_temperatures() {
+    assert_eq!(ColorTemperature::from_kelvin(6500).mired(), 154);
+    assert_eq!(ColorTemperature::from_mired(500).kelvin(), 2000);
+    assert_eq!(ColorTemperature::from_kelvin(0).mired(), u16::MAX);
+
+    let warm = ColorTemperature::from_kelvin(2000);
+    assert_eq!(warm.clamp(&CtRange::AMBIANCE).kelvin(), 2203);
+    assert_eq!(warm.clamp(&CtRange::FULL), warm);
+    assert_eq!(ColorTemperature::from_kelvin(10000).clamp(&CtRange::FULL).mired(), 153);
+    assert_eq!(warm.clamp(&CtRange { min: 500, max: 153 }).mired(), 153);
+}
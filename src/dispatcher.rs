@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread::{self, JoinHandle};
+
+use bridge::Bridge;
+use errors::{Result, HueError};
+use rate_limit::RateLimiter;
+use transport::Transport;
+use ::hue::{CommandResponse, LightCommand, LightId, GroupId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// What a command is sent to
+pub enum Target {
+    /// A light, with `Bridge::set_light_state()`
+    Light(LightId),
+    /// A group, with `Bridge::set_group_state()`
+    Group(GroupId),
+}
+
+#[derive(Debug)]
+/// What happened to a command given to a `Dispatcher`
+pub enum Outcome {
+    /// The command, possibly merged with older ones, was sent to the bridge.
+    /// The response tells which attributes the bridge changed and which it rejected.
+    Delivered(CommandResponse),
+    /// A newer command for the same target came in before this one was sent.
+    /// It has been merged into the newer one.
+    Superseded,
+    /// Sending the command failed
+    Failed(HueError),
+}
+
+#[derive(Debug)]
+/// Tells what happened to a command given to a `Dispatcher`
+pub struct CommandHandle {
+    receiver: Receiver<Outcome>,
+}
+
+impl CommandHandle {
+    /// Waits until the command has been delivered or superseded
+    pub fn wait(self) -> Outcome {
+        self.receiver.recv().unwrap_or_else(|_| Outcome::Failed("the dispatcher stopped".into()))
+    }
+    /// What happened to the command, if it has been delivered or superseded already
+    pub fn try_outcome(&self) -> Option<Outcome> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+struct Pending {
+    command: LightCommand,
+    waiter: Sender<Outcome>,
+}
+
+#[derive(Default)]
+struct Queue {
+    order: VecDeque<Target>,
+    pending: BTreeMap<Target, Pending>,
+    stopping: bool,
+}
+
+/// Sends commands to lights and groups in the background, keeping only the newest command per target.
+///
+/// When a command comes in for a light or group that still has a command waiting to be sent,
+/// the two are merged with `LightCommand::merge()` and the older one is superseded. Targets are
+/// served in the order they first got a waiting command, at the rate the `RateLimiter` allows.
+/// This keeps the lights in step with fast input such as a slider.
+///
+/// The dispatcher limits the rate itself, so give it a `Bridge` without `Bridge::with_rate_limit()`.
+/// Otherwise every command waits for the budgets of both limiters, and a limiter on the bridge
+/// that fails fast or queues makes commands fail.
+///
+/// Dropping the dispatcher sends the commands that are still waiting before it returns.
+pub struct Dispatcher {
+    shared: Arc<(Mutex<Queue>, Condvar)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Dispatcher {
+    /// Creates a dispatcher sending through `bridge` at the rate recommended by Philips
+    pub fn new<T: Transport + Send + 'static>(bridge: Bridge<T>) -> Self {
+        Dispatcher::with_limiter(bridge, RateLimiter::default())
+    }
+    /// Creates a dispatcher sending through `bridge` at the rate `limiter` allows.
+    ///
+    /// A limiter that fails fast or has a bounded queue makes commands fail when the bridge
+    /// is busy, which is usually not wanted here.
+    pub fn with_limiter<T: Transport + Send + 'static>(bridge: Bridge<T>, limiter: RateLimiter) -> Self {
+        let shared = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let theirs = shared.clone();
+        let worker = thread::spawn(move || work(&bridge, &limiter, &theirs));
+        Dispatcher {
+            shared,
+            worker: Some(worker),
+        }
+    }
+    /// Sends the command to the light when it's its turn
+    pub fn set_light_state(&self, id: LightId, command: LightCommand) -> CommandHandle {
+        self.submit(Target::Light(id), command)
+    }
+    /// Sends the command to the group when it's its turn
+    pub fn set_group_state(&self, id: GroupId, command: LightCommand) -> CommandHandle {
+        self.submit(Target::Group(id), command)
+    }
+    /// Sends the command to the target when it's its turn
+    pub fn submit(&self, target: Target, command: LightCommand) -> CommandHandle {
+        let (sender, receiver) = mpsc::channel();
+        let (ref lock, ref cvar) = *self.shared;
+        let mut queue = lock.lock().unwrap();
+        let command = match queue.pending.remove(&target) {
+            Some(older) => {
+                let _ = older.waiter.send(Outcome::Superseded);
+                older.command.merge(&command)
+            }
+            None => {
+                queue.order.push_back(target);
+                command
+            }
+        };
+        queue.pending.insert(target, Pending { command, waiter: sender });
+        cvar.notify_one();
+        CommandHandle { receiver }
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.shared.0.lock().unwrap().stopping = true;
+        self.shared.1.notify_one();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work<T: Transport>(bridge: &Bridge<T>, limiter: &RateLimiter, shared: &(Mutex<Queue>, Condvar)) {
+    let (ref lock, ref cvar) = *shared;
+    loop {
+        let target = {
+            let mut queue = lock.lock().unwrap();
+            while queue.order.is_empty() && !queue.stopping {
+                queue = cvar.wait(queue).unwrap();
+            }
+            match queue.order.front() {
+                Some(&target) => target,
+                None => return,
+            }
+        };
+        // Wait for the budget before taking the command, so newer commands can still be merged into it
+        let allowed: Result<()> = match target {
+            Target::Light(id) => limiter.acquire_light(&format!("/lights/{}/state", id)),
+            Target::Group(id) => limiter.acquire_group(&format!("/groups/{}/action", id)),
+        };
+        let pending = {
+            let mut queue = lock.lock().unwrap();
+            queue.order.pop_front();
+            queue.pending.remove(&target).unwrap()
+        };
+        let result = allowed.and_then(|_| match target {
+            Target::Light(id) => bridge.set_light_state(id, &pending.command),
+            Target::Group(id) => bridge.set_group_state(id, &pending.command),
+        });
+        let _ = pending.waiter.send(match result {
+            Ok(success) => Outcome::Delivered(success),
+            Err(e) => Outcome::Failed(e),
+        });
+    }
+}
+
+#[test]
+fn coalescing() {
+    use std::time::Duration;
+    use transport::{Method, Response};
+
+    // Blocks every request until the test lets it through
+    struct Gate(Mutex<(Sender<Vec<u8>>, Receiver<()>)>);
+    impl Transport for Gate {
+        fn send(&self, _: Method, _: &str, body: Option<&[u8]>) -> Result<Response> {
+            let gate = self.0.lock().unwrap();
+            gate.0.send(body.unwrap().to_vec()).unwrap();
+            gate.1.recv().unwrap();
+            Ok(Response {
+                status: 200,
+                body: br#"[{"success":{"/lights/1/state/bri":1}}]"#.to_vec(),
+            })
+        }
+    }
+
+    let (sent, bodies) = mpsc::channel();
+    let (open, opened) = mpsc::channel();
+    let bridge = Bridge::with_transport("test", "hello", Gate(Mutex::new((sent, opened))));
+    let dispatcher = Dispatcher::new(bridge);
+    let light = LightId::from(1);
+
+    let first = dispatcher.set_light_state(light, LightCommand::default().on().with_bri(10));
+    assert_eq!(bodies.recv_timeout(Duration::from_secs(5)).unwrap(), br#"{"on":true,"bri":10}"#);
+    // The first command is being sent, so these wait and get merged
+    let second = dispatcher.set_light_state(light, LightCommand::default().with_bri(20).with_ct(300));
+    let third = dispatcher.set_light_state(light, LightCommand::default().with_bri(30));
+    open.send(()).unwrap();
+    open.send(()).unwrap();
+    assert_eq!(bodies.recv_timeout(Duration::from_secs(5)).unwrap(), br#"{"bri":30,"ct":300}"#);
+
+    assert!(matches!(first.wait(), Outcome::Delivered(_)));
+    assert!(matches!(second.wait(), Outcome::Superseded));
+    assert!(matches!(third.wait(), Outcome::Delivered(_)));
+}
@@ -2,6 +2,8 @@ use hyper;
 use std::convert::From;
 use serde_json;
 use std::io;
+use std::fmt;
+use std::time::Duration;
 
 impl From<::json::Error> for HueError {
     fn from(e: ::json::Error) -> HueError {
@@ -30,6 +32,54 @@ error_chain! {
             description("bridge error")
             display("Bridge error {:?} on {}: {}", error, address, description)
         }
+        /// A transition time that can't be represented in the deciseconds the bridge uses
+        TransitionTimeOutOfRange(time: Duration) {
+            description("transition time out of range")
+            display("Transition time {:?} is longer than the maximum of 6553.5 seconds", time)
+        }
+        /// The bridge was still searching for new lights when the timeout expired
+        SearchTimedOut {
+            description("search for new lights timed out")
+            display("The bridge was still searching for new lights when the timeout expired")
+        }
+        /// A search for more device IDs than the bridge accepts, which wasn't sent. See `Bridge::search_for_new_lights()`.
+        TooManyDeviceIds(count: usize) {
+            description("too many device IDs")
+            display("Can't search for {} device IDs, the bridge accepts at most 10", count)
+        }
+        /// A resource that wasn't created because the bridge has no room for it. See `Bridge::with_capacity_check()`.
+        NoCapacity(address: String) {
+            description("no capacity left on the bridge")
+            display("The bridge has no room for another resource in {}", address)
+        }
+        /// A `LightCommand` with values the bridge would reject. See `LightCommand::validate()`.
+        InvalidLightCommand(violations: Vec<Violation>) {
+            description("invalid light command")
+            display("Invalid light command: {}", violations.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "))
+        }
+        /// A command that wasn't sent because too many commands were sent already. See `RateLimiter`.
+        RateLimited(address: String) {
+            description("rate limited")
+            display("Too many commands sent, the command to {} was not sent", address)
+        }
+        /// A `Budget` that allows no commands. See `RateLimiter::with_budgets()`.
+        InvalidBudget(budget: String) {
+            description("invalid rate limit budget")
+            display("Invalid rate limit budget {}, it has to allow a positive rate and a burst of at least 1", budget)
+        }
+        /// A string that isn't a valid colour
+        InvalidColor(color: String) {
+            description("invalid colour")
+            display("Invalid colour {:?}", color)
+        }
+        /// A string that isn't a valid time pattern, or a time pattern with values out of range
+        InvalidTimePattern(pattern: String, reason: String) {
+            description("invalid time pattern")
+            display("Invalid time pattern {:?}: {}", pattern, reason)
+        }
     }
 
     foreign_links {
@@ -39,6 +89,45 @@ error_chain! {
     }
 }
 
+impl HueError {
+    /// Whether sending the request again might succeed.
+    ///
+    /// This is the case for IO errors, such as timeouts, and internal errors of the bridge.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.0,
+                 HueErrorKind::IOError(_) |
+                 HueErrorKind::HyperError(hyper::Error::Io(_)) |
+                 HueErrorKind::BridgeError { error: BridgeError::InternalError, .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A single problem found by `LightCommand::validate()`
+pub enum Violation {
+    /// A value outside the range the bridge accepts
+    OutOfRange {
+        /// The name of the field
+        field: &'static str,
+        /// The value of the field
+        value: String,
+        /// The range the value has to be in
+        range: String,
+    },
+    /// Two fields that can't be set in the same command, such as `hue` and `hue_inc`
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::OutOfRange { field, ref value, ref range } => {
+                write!(f, "{} is {} but has to be {}", field, value, range)
+            }
+            Violation::Conflict(a, b) => write!(f, "{} and {} can't be set together", a, b),
+        }
+    }
+}
+
 macro_rules! error_enum {
     (
         $(#[$meta:meta])*
@@ -0,0 +1,275 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::{from_reader, from_slice, to_vec, to_writer_pretty};
+
+use errors::Result;
+use transport::{Method, Response, Transport};
+use ::hue::JsonValue;
+
+/// What a username in a recorded request or response is replaced with
+pub const REDACTED_USERNAME: &str = "<username>";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A request sent to the bridge together with the response to it
+pub struct Exchange {
+    /// The HTTP method of the request
+    pub method: String,
+    /// The path of the request, without the address of the bridge
+    pub path: String,
+    /// The body of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<JsonValue>,
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The body of the response. Bodies that aren't JSON are kept as a string.
+    pub response: JsonValue,
+}
+
+/// Replaces the username in a path, returning the path and the username
+fn redact_path(path: &str) -> (String, Option<String>) {
+    let mut segments: Vec<&str> = path.split('/').collect();
+    let username = match segments.get(1..3) {
+        Some(&["api", username]) if !username.is_empty() => Some(username.to_owned()),
+        _ => None,
+    };
+    if username.is_some() {
+        segments[2] = REDACTED_USERNAME;
+    }
+    (segments.join("/"), username)
+}
+
+/// The string with the usernames replaced, if it is one of them or a path with one of them as a segment
+fn redact_str(s: &str, usernames: &[String]) -> Option<String> {
+    let is_username = |part: &str| usernames.iter().any(|u| u == part);
+    if is_username(s) {
+        Some(REDACTED_USERNAME.to_owned())
+    } else if s.starts_with('/') && s.split('/').any(&is_username) {
+        let parts: Vec<&str> = s.split('/').map(|part| if is_username(part) { REDACTED_USERNAME } else { part }).collect();
+        Some(parts.join("/"))
+    } else {
+        None
+    }
+}
+
+/// Replaces the usernames in the strings and object keys of a JSON value
+fn redact_value(value: &mut JsonValue, usernames: &[String]) {
+    match *value {
+        JsonValue::String(ref mut s) => {
+            if let Some(redacted) = redact_str(s, usernames) {
+                *s = redacted;
+            }
+        }
+        JsonValue::Array(ref mut values) => {
+            for v in values {
+                redact_value(v, usernames);
+            }
+        }
+        JsonValue::Object(ref mut map) => {
+            let entries = ::std::mem::take(map);
+            for (key, mut v) in entries {
+                redact_value(&mut v, usernames);
+                map.insert(redact_str(&key, usernames).unwrap_or(key), v);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Parses a body as JSON with the usernames replaced. Bodies that aren't JSON are kept as is.
+fn redact_body(body: &[u8], usernames: &[String]) -> JsonValue {
+    match from_slice(body) {
+        Ok(mut value) => {
+            redact_value(&mut value, usernames);
+            value
+        }
+        Err(_) => JsonValue::String(String::from_utf8_lossy(body).into_owned()),
+    }
+}
+
+/// The usernames handed out in a response to registering a user
+fn registered_usernames(body: &[u8]) -> Vec<String> {
+    from_slice::<Vec<JsonValue>>(body)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|r| r.pointer("/success/username").and_then(JsonValue::as_str).map(ToOwned::to_owned))
+        .collect()
+}
+
+/// Reads the exchanges saved by `Recording::save()`
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Exchange>> {
+    Ok(from_reader(File::open(path)?)?)
+}
+
+#[derive(Debug)]
+/// A `Transport` that keeps every request sent through it and the response to it.
+///
+/// Usernames are replaced with `REDACTED_USERNAME` in the paths, and in the bodies wherever a JSON string
+/// or key is a username or a path containing one, so the recording can be shared. Other data, such as
+/// the addresses and names in the configuration, is kept as is.
+pub struct Recording<T> {
+    transport: T,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<T: Transport> Recording<T> {
+    /// Records the requests sent through `transport`
+    pub fn new(transport: T) -> Self {
+        Recording {
+            transport,
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+    /// The transport the requests are sent through
+    pub fn inner(&self) -> &T {
+        &self.transport
+    }
+    /// The exchanges recorded so far
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+    /// Writes the exchanges recorded so far to a fixture file, to be read by `Replay::from_file()`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(to_writer_pretty(File::create(path)?, &*self.exchanges.lock().unwrap())?)
+    }
+}
+
+impl<T: Transport> Transport for Recording<T> {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        let resp = self.transport.send(method, path, body)?;
+        let (path, username) = redact_path(path);
+        let mut usernames: Vec<String> = username.into_iter().collect();
+        usernames.extend(registered_usernames(&resp.body));
+
+        self.exchanges.lock().unwrap().push(Exchange {
+            method: method.as_str().to_owned(),
+            path,
+            body: body.map(|b| redact_body(b, &usernames)),
+            status: resp.status,
+            response: redact_body(&resp.body, &usernames),
+        });
+        Ok(resp)
+    }
+}
+
+#[derive(Debug)]
+/// A `Transport` answering requests with recorded exchanges instead of a bridge.
+///
+/// A request is answered by the first exchange not used yet with the same method, path and body,
+/// ignoring the username. The same requests in the same order
+/// therefore always get the same responses. Requests that weren't recorded fail.
+pub struct Replay {
+    /// The exchanges and whether they have been used
+    exchanges: Mutex<Vec<(Exchange, bool)>>,
+}
+
+impl Replay {
+    /// Replays the exchanges
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        Replay { exchanges: Mutex::new(exchanges.into_iter().map(|e| (e, false)).collect()) }
+    }
+    /// Replays the exchanges of a fixture file written by `Recording::save()`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        load(path).map(Replay::new)
+    }
+    /// The exchanges that haven't been used yet
+    pub fn remaining(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().iter().filter(|e| !e.1).map(|e| e.0.clone()).collect()
+    }
+}
+
+impl Transport for Replay {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        let (path, username) = redact_path(path);
+        let usernames: Vec<String> = username.into_iter().collect();
+        let body = body.map(|b| redact_body(b, &usernames));
+
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let exchange = exchanges.iter_mut()
+            .find(|e| !e.1 && e.0.method == method.as_str() && e.0.path == path && e.0.body == body)
+            .ok_or_else(|| format!("no recorded response to {} {}", method.as_str(), path))?;
+        exchange.1 = true;
+        let response = &exchange.0.response;
+        Ok(Response {
+            status: exchange.0.status,
+            body: match *response {
+                JsonValue::String(ref text) => text.clone().into_bytes(),
+                _ => to_vec(response)?,
+            },
+        })
+    }
+}
+
+#[test]
+fn record_and_replay() {
+    use bridge::{self, Bridge};
+    use ::hue::LightCommand;
+
+    struct Fake;
+    impl Transport for Fake {
+        fn send(&self, method: Method, path: &str, _: Option<&[u8]>) -> Result<Response> {
+            let body: &[u8] = match (method, path) {
+                (Method::Post, "/api") => br#"[{"success":{"username":"s3cr3t"}}]"#,
+                (Method::Put, _) => br#"[{"success":{"/lights/1/state/on":true}}]"#,
+                _ => br#"{"whitelist":{"s3cr3t":{"name":"test"}}}"#,
+            };
+            Ok(Response { status: 200, body: body.to_vec() })
+        }
+    }
+
+    let recording = Recording::new(Fake);
+    let username = bridge::register_user_with_transport(&recording, "test").unwrap();
+    let bridge = Bridge::with_transport("10.0.0.2", username, &recording);
+    bridge.set_light_state(1.into(), &LightCommand::default().on()).unwrap();
+    bridge.transport().send(Method::Get, "/api/s3cr3t/config", None).unwrap();
+
+    let exchanges = recording.exchanges();
+    assert_eq!(exchanges[0].response[0]["success"]["username"], REDACTED_USERNAME);
+    assert_eq!(exchanges[1].path, "/api/<username>/lights/1/state");
+    assert!(exchanges[2].response["whitelist"].get(REDACTED_USERNAME).is_some());
+    assert!(!to_vec(&exchanges).unwrap().windows(6).any(|w| w == b"s3cr3t"));
+
+    // Replaying works with another username
+    let replay = Replay::new(exchanges);
+    let bridge = Bridge::with_transport("192.168.1.20", "other", replay);
+    assert!(bridge.set_light_state(1.into(), &LightCommand::default().off()).is_err());
+    assert!(bridge.set_light_state(1.into(), &LightCommand::default().on()).is_ok());
+    assert!(bridge.set_light_state(1.into(), &LightCommand::default().on()).is_err());
+    assert_eq!(bridge.transport().remaining().len(), 2);
+}
+
+#[test]
+fn recorded_fixtures() {
+    use bridge::{self, Bridge};
+    use ::hue::{GroupId, GroupType, RoomClass};
+
+    let replay = Replay::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/mock_bridge.json")).unwrap();
+    let username = bridge::register_user_with_transport(&replay, "philipshue#fixtures").unwrap();
+    let bridge = Bridge::with_transport("10.0.0.2", username, &replay);
+    let group = bridge.create_group("Living room".to_owned(), vec![1.into(), 2.into()], GroupType::Room,
+                                    Some(RoomClass::LivingRoom))
+        .unwrap();
+    assert_eq!(group, GroupId::from(1));
+    assert_eq!(bridge.get_full_state().unwrap().lights.len(), 2);
+    assert_eq!(bridge.get_all_lights().unwrap().len(), 2);
+    assert!(matches!(bridge.get_all_groups().unwrap()[&group].class, Some(RoomClass::LivingRoom)));
+    assert_eq!(bridge.get_configuration().unwrap().name, "Mock bridge");
+    assert!(replay.remaining().is_empty());
+}
+
+#[test]
+fn redacting() {
+    let usernames = ["s3cr3t".to_owned()];
+    let body = br#"{"name":"s3cr3t light","whitelist":{"s3cr3t":{"name":"app"}},"owner":"s3cr3t",
+                    "links":["/api/s3cr3t/lights/1"],"description":"not s3cr3t"}"#;
+    let redacted = redact_body(body, &usernames);
+    assert_eq!(redacted, ::serde_json::json!({
+        "name": "s3cr3t light",
+        "whitelist": {"<username>": {"name": "app"}},
+        "owner": "<username>",
+        "links": ["/api/<username>/lights/1"],
+        "description": "not s3cr3t"
+    }));
+    assert_eq!(redact_body(b"s3cr3t", &usernames), JsonValue::String("s3cr3t".to_owned()));
+}
@@ -1,5 +1,208 @@
 use serde::de::{Deserialize, Deserializer};
 
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident = $s:expr),+;
+            $(#[$ometa:meta])* $other:ident
+        }
+    ) => (
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)+
+            $(#[$ometa])* $other(String)
+        }
+        impl $name {
+            /// The string the bridge uses for this value
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $($name::$variant => $s,)+
+                    $name::$other(ref s) => s
+                }
+            }
+        }
+        impl<'a> From<&'a str> for $name {
+            fn from(s: &'a str) -> Self {
+                match s {
+                    $($s => $name::$variant,)+
+                    _ => $name::$other(s.to_owned())
+                }
+            }
+        }
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.as_str().fmt(f)
+            }
+        }
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map(|s| $name::from(&*s))
+            }
+        }
+    );
+}
+
+macro_rules! id_type {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($inner:ty);
+    ) => (
+        $(#[$meta])*
+        ///
+        /// IDs are sent to and received from the bridge as strings.
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub $inner);
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+        impl FromStr for $name {
+            type Err = <$inner as FromStr>::Err;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map($name)
+            }
+        }
+        impl From<$inner> for $name {
+            fn from(id: $inner) -> Self {
+                $name(id)
+            }
+        }
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                use serde::de::Error;
+                let s = match JsonValue::deserialize(deserializer)? {
+                    JsonValue::String(s) => s,
+                    JsonValue::Number(n) => n.to_string(),
+                    v => return Err(D::Error::custom(format!("invalid ID {}", v))),
+                };
+                s.parse().map_err(|_| D::Error::custom(format!("invalid ID {:?}", s)))
+            }
+        }
+    );
+}
+
+macro_rules! numeric_id {
+    ($(#[$meta:meta])* pub struct $name:ident;) => (
+        id_type!{
+            $(#[$meta])*
+            pub struct $name(usize);
+        }
+        impl Copy for $name {}
+    );
+}
+
+macro_rules! string_id {
+    ($(#[$meta:meta])* pub struct $name:ident;) => (
+        id_type!{
+            $(#[$meta])*
+            pub struct $name(String);
+        }
+        impl $name {
+            /// The ID as a string slice
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+        impl<'a> From<&'a str> for $name {
+            fn from(id: &'a str) -> Self {
+                $name(id.to_owned())
+            }
+        }
+    );
+}
+
+numeric_id!{
+    /// The ID of a light
+    pub struct LightId;
+}
+numeric_id!{
+    /// The ID of a group
+    pub struct GroupId;
+}
+numeric_id!{
+    /// The ID of a sensor
+    pub struct SensorId;
+}
+numeric_id!{
+    /// The ID of a rule
+    pub struct RuleId;
+}
+numeric_id!{
+    /// The ID of a schedule
+    pub struct ScheduleId;
+}
+numeric_id!{
+    /// The ID of a resourcelink
+    pub struct ResourceLinkId;
+}
+string_id!{
+    /// The ID of a scene
+    pub struct SceneId;
+}
+string_id!{
+    /// The name of a user registered on the bridge, used to authenticate all requests
+    pub struct Username;
+}
+
+impl GroupId {
+    /// The special group 0, which contains all lights known to the bridge
+    pub const ALL: GroupId = GroupId(0);
+}
+
+string_enum!{
+    /// The [alert effect](http://www.developers.meethue.com/documentation/core-concepts#some_extra_fun_stuff) of a light
+    pub enum Alert {
+        /// No alert effect
+        None = "none",
+        /// The light performs one breathe cycle
+        Select = "select",
+        /// The light performs breathe cycles for 15 seconds
+        LSelect = "lselect";
+        /// A value this crate doesn't know about yet
+        Unknown
+    }
+}
+
+string_enum!{
+    /// The dynamic effect of a light
+    pub enum Effect {
+        /// No effect
+        None = "none",
+        /// The light cycles through all hues using the current brightness and saturation
+        ColorLoop = "colorloop";
+        /// A value this crate doesn't know about yet
+        Unknown
+    }
+}
+
+string_enum!{
+    /// The colour mode of a light, telling which of its colour values is in effect
+    pub enum ColorMode {
+        /// Hue and saturation
+        Hs = "hs",
+        /// x and y coordinates in colour space
+        Xy = "xy",
+        /// Colour temperature
+        Ct = "ct";
+        /// A value this crate doesn't know about yet
+        Unknown
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// The state of the light with similar structure to `LightCommand`
 pub struct LightState {
@@ -20,20 +223,49 @@ pub struct LightState {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ct: Option<u16>,
     /// The [alert effect](http://www.developers.meethue.com/documentation/core-concepts#some_extra_fun_stuff)
-    pub alert: String,
-    /// The dynamic effect of the light. It can be either "none" or "colorloop"
+    pub alert: Alert,
+    /// The dynamic effect of the light.
     ///
-    /// If "colorloop", the light will cycle hues
+    /// If `ColorLoop`, the light will cycle hues
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub effect: Option<String>,
-    /// The current colour mode either: "hs" for hue and saturation, "xy" for x and y coordinates in colour space, or "ct" for colour temperature
+    pub effect: Option<Effect>,
+    /// The current colour mode telling whether `hue` and `sat`, `xy` or `ct` is in effect
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub colormode: Option<String>,
+    pub colormode: Option<ColorMode>,
     /// Whether the light can be reached by the bridge
     pub reachable: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl LightState {
+    /// The colour of the light in the representation given by `colormode`.
+    ///
+    /// Lights that can't change colour have no colour mode and thus no colour.
+    pub fn color(&self) -> Option<Color> {
+        match self.colormode {
+            Some(ColorMode::Hs) => match (self.hue, self.sat) {
+                (Some(hue), Some(sat)) => Some(Color::Hs { hue, sat }),
+                _ => None,
+            },
+            Some(ColorMode::Xy) => self.xy.map(Color::Xy),
+            Some(ColorMode::Ct) => self.ct.map(Color::Ct),
+            _ => None,
+        }
+    }
+    /// The colour the light is showing in sRGB, taking its brightness into account.
+    ///
+    /// A light that is off is black and a light without colour is white.
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        if !self.on {
+            return (0, 0, 0);
+        }
+        match self.color() {
+            Some(c) => c.to_rgb(self.bri),
+            None => Color::Xy(color::WHITE).to_rgb(self.bri),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 /// The state of the light. Same as `LightState` except there's no `reachable` field.
 pub struct LightStateChange {
     /// Whether the light is on
@@ -56,15 +288,20 @@ pub struct LightStateChange {
     pub ct: Option<u16>,
     /// The [alert effect](http://www.developers.meethue.com/documentation/core-concepts#some_extra_fun_stuff)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub alert: Option<String>,
-    /// The dynamic effect of the light. It can be either "none" or "colorloop"
+    pub alert: Option<Alert>,
+    /// The dynamic effect of the light.
     ///
-    /// If "colorloop", the light will cycle hues
+    /// If `ColorLoop`, the light will cycle hues
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub effect: Option<String>,
-    /// The current colour mode either: "hs" for hue and saturation, "xy" for x and y coordinates in colour space, or "ct" for colour temperature
+    pub effect: Option<Effect>,
+    /// The current colour mode telling whether `hue` and `sat`, `xy` or `ct` is in effect
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub colormode: Option<String>,
+    pub colormode: Option<ColorMode>,
+    /// Duration of the transition from the current state to the new state.
+    ///
+    /// It's sent to the bridge in deciseconds and defaults to 400 ms. Use `Duration::from_secs(0)` for instant changes.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "transitiontime")]
+    pub transitiontime: Option<Duration>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -79,7 +316,247 @@ pub struct Light {
     /// Unique ID of the device
     pub uniqueid: String,
     /// The state of the light (See `LightState` for more)
-    pub state: LightState
+    pub state: LightState,
+    /// What the light is capable of. Only reported by newer bridges.
+    #[serde(default)]
+    pub capabilities: Option<LightCapabilities>,
+}
+
+impl Light {
+    /// The colour gamut of the light.
+    ///
+    /// It's taken from the capabilities of the light if reported, or else from its model.
+    /// For unknown models, gamut C is assumed, which is the gamut of the newest lights.
+    pub fn gamut(&self) -> Gamut {
+        let control = self.capabilities.as_ref().map(|c| &c.control);
+        control.and_then(|c| c.colorgamut)
+            .map(|g| Gamut { red: g[0], green: g[1], blue: g[2] })
+            .or_else(|| control.and_then(|c| c.colorgamuttype.as_ref()).and_then(|t| Gamut::from_name(t)))
+            .or_else(|| Gamut::for_model(&self.modelid))
+            .unwrap_or(Gamut::C)
+    }
+    /// The colour temperatures the light can show.
+    ///
+    /// It's taken from the capabilities of the light if reported, or else from its model.
+    /// For unknown models, the full range the bridge accepts is assumed.
+    pub fn ct_range(&self) -> CtRange {
+        self.capabilities.as_ref().and_then(|c| c.control.ct)
+            .or_else(|| CtRange::for_model(&self.modelid))
+            .unwrap_or(CtRange::FULL)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// What a light is capable of
+pub struct LightCapabilities {
+    /// How the light can be controlled
+    pub control: LightControl,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+/// The colour capabilities of a light
+pub struct LightControl {
+    /// The name of the gamut of the light: "A", "B", "C" or "other"
+    #[serde(default)]
+    pub colorgamuttype: Option<String>,
+    /// The red, green and blue corners of the gamut of the light
+    #[serde(default)]
+    pub colorgamut: Option<[(f32, f32); 3]>,
+    /// The colour temperatures the light can show
+    #[serde(default)]
+    pub ct: Option<CtRange>,
+}
+
+use color::{self, Gamut, Color, ColorTemperature, CtRange};
+
+/// The values the bridge accepts for the numeric attributes of a light state and their increments.
+/// Transition times are in deciseconds and `xy` ranges apply to both coordinates.
+const ATTRIBUTE_RANGES: &[(&str, f64, f64)] = &[
+    ("bri", 1., 254.),
+    ("hue", 0., 65535.),
+    ("sat", 0., 254.),
+    ("xy", 0., 1.),
+    ("ct", 153., 500.),
+    ("bri_inc", -254., 254.),
+    ("hue_inc", -65534., 65534.),
+    ("sat_inc", -254., 254.),
+    ("xy_inc", -0.5, 0.5),
+    ("ct_inc", -65534., 65534.),
+    ("transitiontime", 0., 65535.),
+];
+
+/// The smallest and largest value the bridge accepts for a numeric attribute of a light state, such as `bri` or `ct_inc`
+pub fn attribute_range(name: &str) -> Option<(f64, f64)> {
+    ATTRIBUTE_RANGES.iter().find(|r| r.0 == name).map(|r| (r.1, r.2))
+}
+
+/// Converts a duration to the deciseconds the bridge uses for transition times, rounding to the nearest decisecond.
+///
+/// Fails if the duration is longer than 6553.5 seconds.
+pub fn to_deciseconds(d: Duration) -> ::errors::Result<u16> {
+    match rounded_deciseconds(d) {
+        Some(ds) if ds <= u16::MAX as u64 => Ok(ds as u16),
+        _ => Err(::errors::HueErrorKind::TransitionTimeOutOfRange(d).into()),
+    }
+}
+
+/// The duration in deciseconds, rounded to the nearest decisecond, unless that overflows
+fn rounded_deciseconds(d: Duration) -> Option<u64> {
+    d.as_secs()
+        .checked_mul(10)
+        .and_then(|ds| ds.checked_add((d.subsec_nanos() as u64 + 50_000_000) / 100_000_000))
+}
+
+/// Converts the deciseconds the bridge uses for transition times to a duration
+pub fn from_deciseconds(ds: u16) -> Duration {
+    Duration::from_millis(ds as u64 * 100)
+}
+
+/// (De)serializes an optional `Duration` as a transition time in deciseconds
+mod transitiontime {
+    use std::time::Duration;
+    use serde::{Serializer, Deserialize, Deserializer};
+    use serde::ser::Error;
+
+    pub fn serialize<S: Serializer>(t: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match *t {
+            Some(t) => serializer.serialize_u16(super::to_deciseconds(t).map_err(|e| S::Error::custom(e.to_string()))?),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Option::<u16>::deserialize(deserializer).map(|t| t.map(super::from_deciseconds))
+    }
+}
+
+/// A point in time reported by the bridge, in UTC
+pub type Timestamp = DateTime<Utc>;
+
+/// (De)serializes the timestamps of the bridge, such as "2014-07-17T11:50:13", where "none" means there isn't one
+mod timestamp {
+    use chrono::{NaiveDateTime, TimeZone, Utc};
+    use serde::{Serializer, Deserialize, Deserializer};
+    use serde::de::Error;
+    use super::Timestamp;
+
+    const FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+    /// Parses a timestamp, returning `Ok(None)` for "none"
+    pub fn parse(s: &str) -> Result<Option<NaiveDateTime>, ::chrono::ParseError> {
+        match s {
+            "none" | "" => Ok(None),
+            s => NaiveDateTime::parse_from_str(s.trim_end_matches('Z'), "%Y-%m-%dT%H:%M:%S%.f").map(Some),
+        }
+    }
+
+    fn deserialize_naive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => parse(&s).map_err(|e| D::Error::custom(format!("invalid timestamp {:?}: {}", s, e))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn serialize<S: Serializer>(t: &Option<Timestamp>, serializer: S) -> Result<S::Ok, S::Error> {
+        match *t {
+            Some(ref t) => serializer.collect_str(&t.format(FORMAT)),
+            None => serializer.serialize_str("none"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Timestamp>, D::Error> {
+        deserialize_naive(deserializer).map(|t| t.map(|t| Utc.from_utc_datetime(&t)))
+    }
+
+    /// For timestamps that are always set
+    pub mod required {
+        use serde::{Serializer, Deserializer};
+        use serde::de::Error;
+        use super::super::Timestamp;
+
+        pub fn serialize<S: Serializer>(t: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(&t.format(super::FORMAT))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+            super::deserialize(deserializer)?.ok_or_else(|| D::Error::custom("missing timestamp"))
+        }
+    }
+
+    /// For timestamps in the local time of the bridge
+    pub mod local {
+        use chrono::NaiveDateTime;
+        use serde::{Serializer, Deserializer};
+
+        pub fn serialize<S: Serializer>(t: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+            match *t {
+                Some(ref t) => serializer.collect_str(&t.format(super::FORMAT)),
+                None => serializer.serialize_str("none"),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+            super::deserialize_naive(deserializer)
+        }
+    }
+}
+
+use chrono::{DateTime, Utc, NaiveDateTime, FixedOffset, TimeZone};
+
+#[derive(Debug, Clone, Deserialize)]
+/// A light found by the last search for new lights
+pub struct NewLight {
+    /// The name given to the new light
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Status of the last search for new lights
+pub enum LastScan {
+    /// The bridge is currently searching
+    Active,
+    /// No search has been done since the bridge was powered on
+    None,
+    /// The time the last search was started
+    Finished(Timestamp),
+}
+
+impl<'de> Deserialize<'de> for LastScan {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        Ok(match &*String::deserialize(deserializer)? {
+            "active" => LastScan::Active,
+            "none" => LastScan::None,
+            time => match timestamp::parse(time) {
+                Ok(Some(t)) => LastScan::Finished(Utc.from_utc_datetime(&t)),
+                Ok(None) => LastScan::None,
+                Err(e) => return Err(D::Error::custom(format!("invalid lastscan {:?}: {}", time, e))),
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The lights found by the last search for new lights, and the status of that search
+pub struct NewLights {
+    /// Status of the last search
+    pub lastscan: LastScan,
+    /// The lights that were found
+    pub lights: BTreeMap<LightId, NewLight>,
+}
+
+impl<'de> Deserialize<'de> for NewLights {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        // The lights and "lastscan" are all keys of the same object
+        let mut map = JsonMap::deserialize(deserializer)?;
+        let lastscan = map.remove("lastscan").ok_or_else(|| D::Error::missing_field("lastscan"))?;
+        Ok(NewLights {
+            lastscan: LastScan::deserialize(lastscan).map_err(D::Error::custom)?,
+            lights: ::serde_json::from_value(JsonValue::Object(map)).map_err(D::Error::custom)?,
+        })
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -107,12 +584,12 @@ pub struct LightCommand {
     pub ct: Option<u16>,
     /// The [alert effect](http://www.developers.meethue.com/documentation/core-concepts#some_extra_fun_stuff)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub alert: Option<String>,
-    /// The dynamic effect of the light. It can be either "none" or "colorloop"
+    pub alert: Option<Alert>,
+    /// The dynamic effect of the light.
     ///
-    /// If "colorloop", the light will cycle hues
+    /// If `ColorLoop`, the light will cycle hues
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub effect: Option<String>,
+    pub effect: Option<Effect>,
     /// Has to be a value between -254 and 254. Increments or decrements the value of the brightness.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bri_inc: Option<i16>,
@@ -121,13 +598,18 @@ pub struct LightCommand {
     pub sat_inc: Option<i16>,
     /// Has to be a value between -65534 and 65534. Increments or decrements the value of the hue.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub hue_inc: Option<i16>,
+    pub hue_inc: Option<i32>,
     /// Has to be a value between -65534 and 65534. Increments or decrements the value of the colour temperature.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ct_inc: Option<i16>,
-    /// Increments or decrements the value of the xy.
+    pub ct_inc: Option<i32>,
+    /// Has to be a value between -0.5 and 0.5. Increments or decrements the value of the xy.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub xy_inc: Option<(i16, i16)>,
+    pub xy_inc: Option<(f32, f32)>,
+    /// Duration of the transition from the current state to the new state.
+    ///
+    /// It's sent to the bridge in deciseconds and defaults to 400 ms. Use `Duration::from_secs(0)` for instant changes.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "transitiontime")]
+    pub transitiontime: Option<Duration>,
 }
 
 impl LightCommand {
@@ -155,16 +637,38 @@ impl LightCommand {
     pub fn with_xy(self, xy: (f32, f32)) -> Self {
         LightCommand { xy: Some(xy), ..self }
     }
+    /// Sets the colour and brightness to the closest match of an sRGB colour the light can show.
+    ///
+    /// The gamut of a light can be found with `Light::gamut()`. See `color::rgb_to_xy()` for more.
+    pub fn with_rgb(self, r: u8, g: u8, b: u8, gamut: &Gamut) -> Self {
+        let (xy, bri) = color::rgb_to_xy(r, g, b, gamut);
+        LightCommand { xy: Some(xy), bri: Some(bri), ..self }
+    }
+    /// Same as `with_rgb()`, but with a colour in hex notation such as "#ff8800"
+    pub fn with_hex(self, hex: &str, gamut: &Gamut) -> ::errors::Result<Self> {
+        let (r, g, b) = color::hex_to_rgb(hex)?;
+        Ok(self.with_rgb(r, g, b, gamut))
+    }
     /// Sets the temperature to set the light to
     pub fn with_ct(self, c: u16) -> Self {
         LightCommand { ct: Some(c), ..self }
     }
+    /// Sets the temperature to the closest one within the range of the light.
+    ///
+    /// The range of a light can be found with `Light::ct_range()`.
+    pub fn with_color_temperature(self, ct: ColorTemperature, range: &CtRange) -> Self {
+        self.with_ct(ct.clamp(range).mired())
+    }
+    /// Sets the temperature in Kelvin to the closest one within the range of the light
+    pub fn with_kelvin(self, kelvin: u32, range: &CtRange) -> Self {
+        self.with_color_temperature(ColorTemperature::from_kelvin(kelvin), range)
+    }
     /// Sets the alert mode to set the light to
-    pub fn with_alert(self, a: String) -> Self {
+    pub fn with_alert(self, a: Alert) -> Self {
         LightCommand { alert: Some(a), ..self }
     }
     /// Sets the effect mode to set the light to
-    pub fn with_effect(self, a: String) -> Self {
+    pub fn with_effect(self, a: Effect) -> Self {
         LightCommand { effect: Some(a), ..self }
     }
     /// Sets the brightness increment value
@@ -172,7 +676,7 @@ impl LightCommand {
         LightCommand { bri_inc: Some(b), ..self }
     }
     /// Sets the hue increment value
-    pub fn with_hue_inc(self, h: i16) -> Self {
+    pub fn with_hue_inc(self, h: i32) -> Self {
         LightCommand { hue_inc: Some(h), ..self }
     }
     /// Sets the saturation increment value
@@ -180,13 +684,175 @@ impl LightCommand {
         LightCommand { sat_inc: Some(s), ..self }
     }
     /// Sets the saturation increment value
-    pub fn with_ct_inc(self, ct: i16) -> Self {
+    pub fn with_ct_inc(self, ct: i32) -> Self {
         LightCommand { ct_inc: Some(ct), ..self }
     }
     /// Sets the x and y increment value
-    pub fn with_xy_inc(self, xy: (i16, i16)) -> Self {
+    pub fn with_xy_inc(self, xy: (f32, f32)) -> Self {
         LightCommand { xy_inc: Some(xy), ..self }
     }
+    /// Sets the duration of the transition to the new state
+    pub fn with_transitiontime(self, t: Duration) -> Self {
+        LightCommand { transitiontime: Some(t), ..self }
+    }
+    /// Checks that all values are in the ranges the bridge accepts, and that no value
+    /// is set together with its increment.
+    ///
+    /// Fails with `HueErrorKind::InvalidLightCommand` listing every violation.
+    pub fn validate(&self) -> ::errors::Result<()> {
+        use errors::Violation::{OutOfRange, Conflict};
+
+        fn check<T: Into<f64> + Copy + fmt::Display>(v: &mut Vec<::errors::Violation>, field: &'static str,
+                                                     value: Option<T>) {
+            let (min, max) = attribute_range(field).expect("attribute without a range");
+            if let Some(value) = value {
+                let n = value.into();
+                if !n.is_finite() || n < min || n > max {
+                    v.push(OutOfRange {
+                        field,
+                        value: value.to_string(),
+                        range: format!("{} to {}", min, max),
+                    });
+                }
+            }
+        }
+
+        let mut v = Vec::new();
+        check(&mut v, "bri", self.bri);
+        check(&mut v, "hue", self.hue);
+        check(&mut v, "sat", self.sat);
+        check(&mut v, "xy", self.xy.map(|xy| xy.0));
+        check(&mut v, "xy", self.xy.map(|xy| xy.1));
+        check(&mut v, "ct", self.ct);
+        check(&mut v, "bri_inc", self.bri_inc);
+        check(&mut v, "hue_inc", self.hue_inc);
+        check(&mut v, "sat_inc", self.sat_inc);
+        check(&mut v, "xy_inc", self.xy_inc.map(|xy| xy.0));
+        check(&mut v, "xy_inc", self.xy_inc.map(|xy| xy.1));
+        check(&mut v, "ct_inc", self.ct_inc);
+        // Checked in the deciseconds that are sent, as the bridge would see them
+        let transitiontime = self.transitiontime.map(|t| rounded_deciseconds(t).map_or(f64::INFINITY, |ds| ds as f64));
+        check(&mut v, "transitiontime", transitiontime);
+
+        let conflicts = [
+            ("bri", self.bri.is_some(), "bri_inc", self.bri_inc.is_some()),
+            ("hue", self.hue.is_some(), "hue_inc", self.hue_inc.is_some()),
+            ("sat", self.sat.is_some(), "sat_inc", self.sat_inc.is_some()),
+            ("xy", self.xy.is_some(), "xy_inc", self.xy_inc.is_some()),
+            ("ct", self.ct.is_some(), "ct_inc", self.ct_inc.is_some()),
+        ];
+        for &(a, a_set, b, b_set) in conflicts.iter() {
+            if a_set && b_set {
+                v.push(Conflict(a, b));
+            }
+        }
+
+        if v.is_empty() {
+            Ok(())
+        } else {
+            Err(::errors::HueErrorKind::InvalidLightCommand(v).into())
+        }
+    }
+    /// Combines this command with a `newer` one into a single command, for when only the end result matters.
+    ///
+    /// Values of the newer command win. Increments are added to the older values, and colours
+    /// of the older command are dropped when the newer one sets a colour in another way.
+    /// The `alert` of the older command is dropped too, as a flash that was superseded shouldn't
+    /// be shown later.
+    ///
+    /// This isn't always the same as sending both in order, as the bridge limits every value
+    /// it applies: `bri_inc` 100 and then -100 takes a light at 200 to 154 when sent one by one,
+    /// but leaves it at 200 when merged.
+    pub fn merge(&self, newer: &LightCommand) -> LightCommand {
+        fn pair<A: Copy, I: Copy>(old: (Option<A>, Option<I>), new: (Option<A>, Option<I>),
+                                  apply: fn(A, I) -> A, add: fn(I, I) -> I) -> (Option<A>, Option<I>) {
+            match (old, new) {
+                (_, (Some(a), i)) => (Some(a), i),
+                ((Some(a), _), (None, Some(i))) => (Some(apply(a, i)), None),
+                ((None, Some(oi)), (None, Some(i))) => (None, Some(add(oi, i))),
+                ((None, None), new) => new,
+                (old, (None, None)) => old,
+            }
+        }
+
+        let mut old = self.clone();
+        let (xy, ct, hs) = (newer.xy.is_some() || newer.xy_inc.is_some(),
+                            newer.ct.is_some() || newer.ct_inc.is_some(),
+                            newer.hue.is_some() || newer.hue_inc.is_some() || newer.sat.is_some() ||
+                            newer.sat_inc.is_some());
+        // The bridge prefers xy over ct over hue and saturation, so only one kind of colour may stay
+        if xy || ct || hs {
+            if !xy {
+                old.xy = None;
+                old.xy_inc = None;
+            }
+            if !ct {
+                old.ct = None;
+                old.ct_inc = None;
+            }
+            if !hs {
+                old.hue = None;
+                old.hue_inc = None;
+                old.sat = None;
+                old.sat_inc = None;
+            }
+        }
+
+        let (bri, bri_inc) = pair((old.bri, old.bri_inc), (newer.bri, newer.bri_inc),
+                                  |a, i| (a as i16).saturating_add(i).clamp(1, 254) as u8,
+                                  |a, b| a.saturating_add(b).clamp(-254, 254));
+        let (sat, sat_inc) = pair((old.sat, old.sat_inc), (newer.sat, newer.sat_inc),
+                                  |a, i| (a as i16).saturating_add(i).clamp(0, 254) as u8,
+                                  |a, b| a.saturating_add(b).clamp(-254, 254));
+        let (hue, hue_inc) = pair((old.hue, old.hue_inc), (newer.hue, newer.hue_inc),
+                                  |a, i| (a as i64 + i as i64).rem_euclid(65536) as u16,
+                                  |a, b| a.saturating_add(b).clamp(-65534, 65534));
+        let (ct, ct_inc) = pair((old.ct, old.ct_inc), (newer.ct, newer.ct_inc),
+                                |a, i| (a as i32).saturating_add(i).clamp(153, 500) as u16,
+                                |a, b| a.saturating_add(b).clamp(-65534, 65534));
+        let (xy, xy_inc) = pair((old.xy, old.xy_inc), (newer.xy, newer.xy_inc),
+                                |a, i| ((a.0 + i.0).clamp(0., 1.), (a.1 + i.1).clamp(0., 1.)),
+                                |a, b| ((a.0 + b.0).clamp(-0.5, 0.5), (a.1 + b.1).clamp(-0.5, 0.5)));
+
+        LightCommand {
+            on: newer.on.or(old.on),
+            bri,
+            hue,
+            sat,
+            xy,
+            ct,
+            alert: newer.alert.clone(),
+            effect: newer.effect.clone().or(old.effect),
+            bri_inc,
+            sat_inc,
+            hue_inc,
+            ct_inc,
+            xy_inc,
+            transitiontime: newer.transitiontime.or(old.transitiontime),
+        }
+    }
+    /// The attributes of the command with the given names, such as "ct" or "bri_inc", with the same transition time.
+    ///
+    /// Use it with `CommandResponse::failed_attributes()` to send only what failed again.
+    pub fn only(&self, attributes: &[&str]) -> LightCommand {
+        let keep = |name: &str| attributes.contains(&name);
+        LightCommand {
+            on: self.on.filter(|_| keep("on")),
+            bri: self.bri.filter(|_| keep("bri")),
+            hue: self.hue.filter(|_| keep("hue")),
+            sat: self.sat.filter(|_| keep("sat")),
+            xy: self.xy.filter(|_| keep("xy")),
+            ct: self.ct.filter(|_| keep("ct")),
+            alert: self.alert.clone().filter(|_| keep("alert")),
+            effect: self.effect.clone().filter(|_| keep("effect")),
+            bri_inc: self.bri_inc.filter(|_| keep("bri_inc")),
+            sat_inc: self.sat_inc.filter(|_| keep("sat_inc")),
+            hue_inc: self.hue_inc.filter(|_| keep("hue_inc")),
+            ct_inc: self.ct_inc.filter(|_| keep("ct_inc")),
+            xy_inc: self.xy_inc.filter(|_| keep("xy_inc")),
+            transitiontime: self.transitiontime,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -277,7 +943,7 @@ pub struct Group {
     /// Name of the group. (Default name is "Group").
     pub name: String,
     /// IDs of all the lights in this group
-    pub lights: Vec<usize>,
+    pub lights: Vec<LightId>,
     #[serde(rename="type")]
     /// Type of the group
     pub group_type: GroupType,
@@ -300,7 +966,7 @@ pub struct GroupCommand {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// IDs of all the lights that should be in the group.
-    pub lights: Vec<usize>,
+    pub lights: Vec<LightId>,
     /// The class of the room. Default is `Other`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub class: Option<RoomClass>
@@ -317,11 +983,11 @@ pub struct GroupState {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bri: Option<u8>,
     /// Last time the state of at least one light in the group was changed.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub lastupdated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "timestamp")]
+    pub lastupdated: Option<Timestamp>,
     /// Last time the group was turned on or off.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub lastswitched: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "timestamp")]
+    pub lastswitched: Option<Timestamp>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -352,16 +1018,16 @@ impl Discovery {
 pub use serde_json::{Map as JsonMap, Value as JsonValue};
 use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Contains information about what can be updated
 pub struct DeviceTypes {
     /// Whether there is an update available for the bridge.
     bridge: bool,
     /// List of lights to be updated.
-    lights: Vec<usize>,
+    lights: Vec<LightId>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Information about software updates on the bridge
 pub struct SoftwareUpdate {
     /// Lets the bridge search for software updates
@@ -379,20 +1045,20 @@ pub struct SoftwareUpdate {
     pub notify: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// A user in the whitelist of a `Configuration`
 pub struct WhitelistUser {
     /// Name of the user. It's what you specify as `devicetype` when registering a user
     pub name: String,
     /// Date this user was last used
-    #[serde(rename="last use date")]
-    pub last_use_date: String,
+    #[serde(rename="last use date", with = "timestamp")]
+    pub last_use_date: Option<Timestamp>,
     /// Date this user was created
-    #[serde(rename="create date")]
-    pub create_date: String,
+    #[serde(rename="create date", with = "timestamp")]
+    pub create_date: Option<Timestamp>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Configuration of the bridge
 pub struct Configuration {
     /// Name of the bridge. This is also its uPnP name.
@@ -400,7 +1066,7 @@ pub struct Configuration {
     /// Contains information about software updates
     pub swupdate: SoftwareUpdate,
     /// A list of all registered users
-    pub whitelist: BTreeMap<String, WhitelistUser>,
+    pub whitelist: BTreeMap<Username, WhitelistUser>,
     /// Version of the hue API on the bridge.
     pub apiversion: String,
     /// Software version of the bridge
@@ -424,10 +1090,11 @@ pub struct Configuration {
     /// Whether the bridge is registered to synchronize data with a portal account.
     pub portalservices: bool,
     /// Current time stored on the bridge.
-    #[serde(rename="UTC")]
-    pub utc: String,
-    /// The local time of the bridge or "none".
-    pub localtime: String,
+    #[serde(rename="UTC", with = "timestamp::required")]
+    pub utc: Timestamp,
+    /// The local time of the bridge, if a timezone has been set.
+    #[serde(with = "timestamp::local")]
+    pub localtime: Option<NaiveDateTime>,
     /// Timezone of the bridge as OlsenIDs (e.g. "Europe/Amsterdam") or "none".
     pub timezone: String,
     /// The current wireless frequency channel used by the bridge. It can take values of 11, 15, 20,25 or 0 if undefined (factory new).
@@ -442,6 +1109,38 @@ pub struct Configuration {
     pub replacesbridgeid: Option<String>,
 }
 
+impl Configuration {
+    /// The offset of the local time of the bridge from UTC, if a timezone has been set.
+    ///
+    /// It's derived from the difference between `localtime` and `utc`, rounded to whole minutes.
+    pub fn utc_offset(&self) -> Option<FixedOffset> {
+        let diff = self.localtime? - self.utc.naive_utc();
+        let minutes = (diff.num_seconds() as f64 / 60.).round() as i32;
+        FixedOffset::east_opt(minutes * 60)
+    }
+    /// The local time of the bridge with its offset from UTC, if a timezone has been set.
+    pub fn local_datetime(&self) -> Option<DateTime<FixedOffset>> {
+        self.utc_offset().map(|offset| self.utc.with_timezone(&offset))
+    }
+    /// Converts a UTC timestamp to the local time of the bridge, if a timezone has been set.
+    pub fn to_local(&self, t: Timestamp) -> Option<DateTime<FixedOffset>> {
+        self.utc_offset().map(|offset| t.with_timezone(&offset))
+    }
+    /// The timezone of the bridge, if one has been set and it's known.
+    #[cfg(feature = "timezones")]
+    pub fn tz(&self) -> Option<::chrono_tz::Tz> {
+        self.timezone.parse().ok()
+    }
+    /// Interprets a time in the local time of the bridge (e.g. from a `TimePattern`) using its timezone.
+    ///
+    /// Returns `None` if the bridge has no known timezone, or if the time doesn't exist or is
+    /// ambiguous in it because of daylight saving time.
+    #[cfg(feature = "timezones")]
+    pub fn localize(&self, t: NaiveDateTime) -> Option<DateTime<::chrono_tz::Tz>> {
+        self.tz().and_then(|tz| tz.from_local_datetime(&t).single())
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 /// Information to set about software updates on the bridge
 pub struct SoftwareUpdateModifier {
@@ -485,8 +1184,8 @@ pub struct ConfigurationModifier {
     ///
     /// **Only modifiable when bridge cannot access the internet.**
     #[serde(rename="UTC")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub utc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "timestamp")]
+    pub utc: Option<Timestamp>,
     /// Timezone of the bridge.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timezone: Option<String>,
@@ -495,30 +1194,29 @@ pub struct ConfigurationModifier {
     pub touchlink: Option<bool>
 }
 
-fn null_value() -> JsonValue{
-    JsonValue::Null
-}
-
 #[derive(Debug, Clone, Deserialize)]
 /// The entire datastore of the bridge.
 pub struct FullState {
     /// All lights on the bridge.
-    pub lights: BTreeMap<usize, Light>,
+    pub lights: BTreeMap<LightId, Light>,
     /// All groups on the bridge.
-    pub groups: BTreeMap<usize, Group>,
+    pub groups: BTreeMap<GroupId, Group>,
     /// The configuration of the bridge.
     pub config: Configuration,
-    /// Not yet fully implemented
-    #[serde(default = "null_value")]
-    pub schedule: JsonValue,
+    /// All schedules on the bridge
+    #[serde(default)]
+    pub schedules: BTreeMap<ScheduleId, Schedule>,
     /// All scenes on the bridge
-    pub scenes: BTreeMap<String, Scene>,
-    /// Not yet fully implemented
-    #[serde(default = "null_value")]
-    pub sensors: JsonValue,
-    /// Not yet fully implemented
-    #[serde(default = "null_value")]
-    pub rules: JsonValue
+    pub scenes: BTreeMap<SceneId, Scene>,
+    /// All sensors on the bridge
+    #[serde(default)]
+    pub sensors: BTreeMap<SensorId, Sensor>,
+    /// All rules on the bridge
+    #[serde(default)]
+    pub rules: BTreeMap<RuleId, Rule>,
+    /// All resourcelinks on the bridge
+    #[serde(default)]
+    pub resourcelinks: BTreeMap<ResourceLinkId, ResourceLink>
 }
 
 /// A [scene](https://developers.meethue.com/documentation/scenes-api)
@@ -529,7 +1227,7 @@ pub struct Scene {
     /// Human readable name given to the scene
     pub name: String,
     /// The IDs of the lights in the scene.
-    pub lights: Vec<usize>,
+    pub lights: Vec<LightId>,
     /// The name of the user that created or last modified the scene
     pub owner: String,
     /// Whether the scene can be deleted automatically by the bridge
@@ -541,11 +1239,12 @@ pub struct Scene {
     pub appdata: Option<AppData>,
     /// Reserved for future use. See Philips Hue documention
     pub picture: Option<String>,
-    /// UTC timestamp of when the scene was last updated
-    pub lastupdated: Option<String>,
+    /// When the scene was last updated
+    #[serde(default, with = "timestamp")]
+    pub lastupdated: Option<Timestamp>,
     /// Light states stored on the scene to be recalled
     #[serde(default)]
-    pub lightstates: BTreeMap<usize, LightStateChange>
+    pub lightstates: BTreeMap<LightId, LightStateChange>
 }
 
 fn non_default<'a, 'de, T, D>(de: D) -> Result<Option<T>, D::Error>
@@ -575,7 +1274,7 @@ pub struct SceneCreater {
     /// Human readable name.
     pub name: String,
     /// IDs of the lights the scene uses.
-    pub lights: Vec<usize>,
+    pub lights: Vec<LightId>,
     /// Whether the bridge can just delete this scene.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recycle: Option<bool>,
@@ -585,9 +1284,9 @@ pub struct SceneCreater {
     /// Picture for the scene
     #[serde(skip_serializing_if = "Option::is_none")]
     pub picture: Option<String>,
-    /// Duration of time (in deciseconds) for the lights to transition from one state to another with this scene.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub transitiontime: Option<u16>
+    /// Duration of the transition of the lights from one state to another with this scene.
+    #[serde(skip_serializing_if = "Option::is_none", with = "transitiontime")]
+    pub transitiontime: Option<Duration>
 }
 #[derive(Debug, Clone, Serialize)]
 /// Struct for modifying a scene (renaming, setting lights, updating their state).
@@ -597,8 +1296,1635 @@ pub struct SceneModifier {
     pub name: Option<String>,
     /// New IDs of the lights the scene uses
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub lights: Option<Vec<usize>>,
+    pub lights: Option<Vec<LightId>>,
     /// If true, it will update the light states in the scene to the states of the actual lights
     #[serde(skip_serializing_if = "::std::ops::Not::not")]
     pub storelightstate: bool
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A [sensor](https://developers.meethue.com/documentation/sensors-api), such as a motion sensor, a dimmer switch or a CLIP sensor
+pub struct Sensor {
+    /// The name of the sensor
+    pub name: String,
+    /// Type name of the sensor (e.g. "ZLLPresence", "ZLLSwitch" or "CLIPGenericStatus")
+    #[serde(rename="type")]
+    pub sensor_type: String,
+    /// The hardware model of the sensor
+    pub modelid: String,
+    /// The manufacturer name of the sensor
+    pub manufacturername: String,
+    /// The version of the software running on the sensor
+    pub swversion: String,
+    /// Unique ID of the device. Not every sensor has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uniqueid: Option<String>,
+    /// The state of the sensor (See `SensorState` for more)
+    #[serde(default)]
+    pub state: SensorState,
+    /// The configuration of the sensor (See `SensorConfig` for more)
+    #[serde(default)]
+    pub config: SensorConfig,
+    /// Whether the sensor can be deleted automatically by the bridge
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recycle: Option<bool>,
+}
+
+impl Sensor {
+    /// The battery level of the sensor in percent, if it's battery powered
+    pub fn battery(&self) -> Option<u8> {
+        self.config.battery
+    }
+    /// Whether the sensor can be reached by the bridge.
+    ///
+    /// Sensors that don't report this (such as most CLIP sensors) are considered reachable.
+    pub fn reachable(&self) -> bool {
+        self.config.reachable.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// The state of a sensor.
+///
+/// Which of the fields are set depends on the type of the sensor.
+pub struct SensorState {
+    /// Last time the state of the sensor was changed
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "timestamp")]
+    pub lastupdated: Option<Timestamp>,
+    /// The last event of a switch, e.g. 1002 for releasing the "on" button of a dimmer switch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buttonevent: Option<u16>,
+    /// Whether presence has been detected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence: Option<bool>,
+    /// Temperature in 0.01 degrees Celsius
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<i32>,
+    /// Relative humidity in 0.01 percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<u16>,
+    /// Light level in 10000 log10 (lux) + 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lightlevel: Option<u16>,
+    /// Whether the light level is below the dark threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dark: Option<bool>,
+    /// Whether the light level is above the daylight threshold, or whether it's daylight for a daylight sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daylight: Option<bool>,
+    /// The state of a `CLIPGenericFlag` sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flag: Option<bool>,
+    /// The state of a `CLIPGenericStatus` sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<i32>,
+    /// Whether a `CLIPOpenClose` sensor is open
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// The configuration of a sensor.
+///
+/// Which of the fields are set depends on the type of the sensor.
+pub struct SensorConfig {
+    /// Whether the sensor is turned on. A sensor that is off doesn't update its state.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on: Option<bool>,
+    /// Whether the sensor can be reached by the bridge
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reachable: Option<bool>,
+    /// The battery level of the sensor in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery: Option<u8>,
+    /// The alert effect of the sensor's LED
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<String>,
+    /// URL of the sensor, for CLIP sensors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Whether the LED of a motion sensor lights up when it detects motion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledindication: Option<bool>,
+    /// Sensitivity of a motion sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensitivity: Option<u8>,
+    /// The light level below which it's considered dark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tholddark: Option<u16>,
+    /// Offset above `tholddark` above which it's considered daylight
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tholdoffset: Option<u16>,
+    /// Latitude of the bridge, for the daylight sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lat: Option<String>,
+    /// Longitude of the bridge, for the daylight sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long: Option<String>,
+    /// Offset in minutes to sunrise, for the daylight sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sunriseoffset: Option<i8>,
+    /// Offset in minutes to sunset, for the daylight sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sunsetoffset: Option<i8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// A [rule](https://developers.meethue.com/documentation/rules-api)
+///
+/// A rule performs its actions when all of its conditions are met.
+pub struct Rule {
+    /// Human readable name of the rule
+    pub name: String,
+    /// The name of the user that created the rule
+    pub owner: String,
+    /// When the rule was created
+    #[serde(with = "timestamp::required")]
+    pub created: Timestamp,
+    /// When the rule was last triggered, if it has been triggered
+    #[serde(with = "timestamp")]
+    pub lasttriggered: Option<Timestamp>,
+    /// How many times the rule has been triggered
+    pub timestriggered: u32,
+    /// Whether the rule is enabled, disabled or has been disabled by the bridge
+    pub status: RuleStatus,
+    /// Whether the rule can be deleted automatically by the bridge
+    #[serde(default)]
+    pub recycle: bool,
+    /// The conditions that all have to be met for the rule to trigger
+    pub conditions: Vec<Condition>,
+    /// The actions that are performed when the rule triggers
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Status of a rule
+pub enum RuleStatus {
+    /// The rule is enabled
+    #[serde(rename = "enabled")]
+    Enabled,
+    /// The rule has been disabled
+    #[serde(rename = "disabled")]
+    Disabled,
+    /// The rule was disabled by the bridge because a resource used in its conditions or actions was deleted
+    #[serde(rename = "resourcedeleted")]
+    ResourceDeleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A condition of a rule
+pub struct Condition {
+    /// The address of the attribute to check, e.g. "/sensors/2/state/buttonevent"
+    pub address: String,
+    /// How to compare the attribute
+    pub operator: Operator,
+    /// The value to compare with. Not used by the `Dx` operator.
+    ///
+    /// `Ddx`, `Stable` and `NotStable` take a time such as "PT00:00:10",
+    /// `In` and `NotIn` a time interval such as "T08:00:00/T10:00:00".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+impl Condition {
+    /// Creates a condition that compares the attribute at `address` with `value`
+    pub fn new<A: Into<String>, V: ToString>(address: A, operator: Operator, value: V) -> Self {
+        Condition {
+            address: address.into(),
+            operator,
+            value: Some(value.to_string()),
+        }
+    }
+    /// Creates a condition that is met when the attribute at `address` has changed
+    pub fn changed<A: Into<String>>(address: A) -> Self {
+        Condition {
+            address: address.into(),
+            operator: Operator::Dx,
+            value: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The operator used by a `Condition`
+pub enum Operator {
+    /// Equal to the value
+    #[serde(rename = "eq")]
+    Eq,
+    /// Greater than the value
+    #[serde(rename = "gt")]
+    Gt,
+    /// Less than the value
+    #[serde(rename = "lt")]
+    Lt,
+    /// The attribute has changed
+    #[serde(rename = "dx")]
+    Dx,
+    /// The attribute has changed, with a delay
+    #[serde(rename = "ddx")]
+    Ddx,
+    /// The attribute hasn't changed for the given time
+    #[serde(rename = "stable")]
+    Stable,
+    /// The attribute has changed within the given time
+    #[serde(rename = "not stable")]
+    NotStable,
+    /// The current time is in the given time interval
+    #[serde(rename = "in")]
+    In,
+    /// The current time is not in the given time interval
+    #[serde(rename = "not in")]
+    NotIn,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// An action of a rule or the command of a schedule.
+///
+/// It's the request sent to the bridge when the rule or schedule triggers.
+pub struct Action {
+    /// The address of the resource, e.g. "/groups/0/action"
+    pub address: String,
+    /// The HTTP method used to perform the action
+    pub method: ActionMethod,
+    /// The body of the request
+    pub body: JsonMap<String, JsonValue>,
+}
+
+impl Action {
+    /// Creates an action that sends the `body` to the `address` with the `method`
+    ///
+    /// ## Example
+    /// ```
+    /// use philipshue::hue::{Action, ActionMethod, LightCommand};
+    ///
+    /// let action = Action::new("/groups/0/action", ActionMethod::Put, &LightCommand::default().on()).unwrap();
+    /// ```
+    pub fn new<A: Into<String>, B: ::serde::Serialize>(address: A, method: ActionMethod, body: &B) -> ::errors::Result<Self> {
+        let body = match ::serde_json::to_value(body)? {
+            JsonValue::Object(map) => map,
+            _ => return Err("The body of an action has to be an object".into()),
+        };
+        Ok(Action {
+            address: address.into(),
+            method,
+            body,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The HTTP method of an `Action`
+pub enum ActionMethod {
+    #[allow(missing_docs)]
+    #[serde(rename = "PUT")]
+    Put,
+    #[allow(missing_docs)]
+    #[serde(rename = "POST")]
+    Post,
+    #[allow(missing_docs)]
+    #[serde(rename = "DELETE")]
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// A rule to be created on the bridge
+pub struct RuleCreater {
+    /// Human readable name of the rule
+    pub name: String,
+    /// The conditions that all have to be met for the rule to trigger
+    pub conditions: Vec<Condition>,
+    /// The actions that are performed when the rule triggers
+    pub actions: Vec<Action>,
+    /// Whether the rule should be enabled. Defaults to enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<RuleStatus>,
+    /// Whether the bridge can just delete this rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recycle: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+/// Struct for modifying a rule. Only the fields that are set will be changed.
+pub struct RuleModifier {
+    /// Name to rename the rule to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The new conditions of the rule. These replace all existing conditions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<Condition>>,
+    /// The new actions of the rule. These replace all existing actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<Action>>,
+    /// Enables or disables the rule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<RuleStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// A [schedule](https://developers.meethue.com/documentation/schedules-api-0)
+///
+/// A schedule sends its command to the bridge at the specified time.
+pub struct Schedule {
+    /// Human readable name of the schedule
+    pub name: String,
+    /// Description of the schedule
+    #[serde(default)]
+    pub description: String,
+    /// The request that is sent when the schedule triggers
+    pub command: Action,
+    /// The time, in local time of the bridge, when the schedule triggers
+    pub localtime: TimePattern,
+    /// When the schedule was created
+    #[serde(default, with = "timestamp")]
+    pub created: Option<Timestamp>,
+    /// Whether the schedule is enabled
+    pub status: ScheduleStatus,
+    /// Whether the schedule is removed after it has triggered. Only used for non-recurring schedules.
+    #[serde(default)]
+    pub autodelete: Option<bool>,
+    /// Whether the schedule can be deleted automatically by the bridge
+    #[serde(default)]
+    pub recycle: bool,
+    /// When the timer of the schedule was started
+    #[serde(default, with = "timestamp")]
+    pub starttime: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Status of a schedule
+pub enum ScheduleStatus {
+    /// The schedule is enabled
+    #[serde(rename = "enabled")]
+    Enabled,
+    /// The schedule is disabled
+    #[serde(rename = "disabled")]
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// A schedule to be created on the bridge
+pub struct ScheduleCreater {
+    /// Human readable name of the schedule. Defaults to "schedule".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Description of the schedule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The request that is sent when the schedule triggers
+    pub command: Action,
+    /// The time, in local time of the bridge, when the schedule triggers
+    pub localtime: TimePattern,
+    /// Whether the schedule should be enabled. Defaults to enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ScheduleStatus>,
+    /// Whether the schedule is removed after it has triggered. Defaults to true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autodelete: Option<bool>,
+    /// Whether the bridge can just delete this schedule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recycle: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+/// Struct for modifying a schedule. Only the fields that are set will be changed.
+pub struct ScheduleModifier {
+    /// Name to rename the schedule to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The new description of the schedule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The new request that is sent when the schedule triggers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<Action>,
+    /// The new time, in local time of the bridge, when the schedule triggers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub localtime: Option<TimePattern>,
+    /// Enables or disables the schedule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ScheduleStatus>,
+    /// Whether the schedule is removed after it has triggered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autodelete: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A time of day, or a duration shorter than a day, as used in a `TimePattern`
+pub struct Time {
+    /// Hours, from 0 to 23
+    pub hour: u8,
+    /// Minutes, from 0 to 59
+    pub minute: u8,
+    /// Seconds, from 0 to 59
+    pub second: u8,
+}
+
+impl Time {
+    /// Creates a `Time`, checking that the values are in range
+    pub fn new(hour: u8, minute: u8, second: u8) -> ::errors::Result<Self> {
+        let t = Time { hour, minute, second };
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(invalid_pattern(t.to_string(), "hours, minutes or seconds out of range"));
+        }
+        Ok(t)
+    }
+    /// Creates a `Time` from a duration, ignoring fractions of a second.
+    ///
+    /// The duration has to be shorter than a day.
+    pub fn from_duration(d: Duration) -> ::errors::Result<Self> {
+        let secs = d.as_secs();
+        if secs >= 24 * 60 * 60 {
+            return Err(invalid_pattern(format!("{:?}", d), "durations have to be shorter than a day"));
+        }
+        Time::new((secs / 3600) as u8, (secs / 60 % 60) as u8, (secs % 60) as u8)
+    }
+    /// The duration from midnight to this time
+    pub fn to_duration(&self) -> Duration {
+        Duration::from_secs(self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64)
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A calendar date, as used in a `TimePattern`
+pub struct Date {
+    #[allow(missing_docs)]
+    pub year: u16,
+    /// Month, from 1 to 12
+    pub month: u8,
+    /// Day of the month, starting at 1
+    pub day: u8,
+}
+
+impl Date {
+    /// Creates a `Date`, checking that the day exists
+    pub fn new(year: u16, month: u8, day: u8) -> ::errors::Result<Self> {
+        let d = Date { year, month, day };
+        let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return Err(invalid_pattern(d.to_string(), "month out of range")),
+        };
+        if day == 0 || day > days {
+            return Err(invalid_pattern(d.to_string(), "day out of range"));
+        }
+        Ok(d)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The days of the week a recurring `TimePattern` applies to.
+///
+/// Days can be combined with `|`, e.g. `Weekdays::SATURDAY | Weekdays::SUNDAY`.
+pub struct Weekdays(u8);
+
+#[allow(missing_docs)]
+impl Weekdays {
+    pub const MONDAY: Weekdays = Weekdays(64);
+    pub const TUESDAY: Weekdays = Weekdays(32);
+    pub const WEDNESDAY: Weekdays = Weekdays(16);
+    pub const THURSDAY: Weekdays = Weekdays(8);
+    pub const FRIDAY: Weekdays = Weekdays(4);
+    pub const SATURDAY: Weekdays = Weekdays(2);
+    pub const SUNDAY: Weekdays = Weekdays(1);
+    /// Monday to Friday
+    pub const WORKDAYS: Weekdays = Weekdays(124);
+    /// Saturday and Sunday
+    pub const WEEKEND: Weekdays = Weekdays(3);
+    /// Every day of the week
+    pub const ALL: Weekdays = Weekdays(127);
+
+    /// Creates `Weekdays` from the bitmask used by the bridge, where Monday is 64 and Sunday is 1.
+    ///
+    /// At least one day has to be set.
+    pub fn from_bits(bits: u8) -> ::errors::Result<Self> {
+        if bits == 0 || bits > 127 {
+            return Err(invalid_pattern(format!("W{:03}", bits), "weekday bitmask has to be between 1 and 127"));
+        }
+        Ok(Weekdays(bits))
+    }
+    /// The bitmask used by the bridge
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+    /// Whether all the days in `other` are also in `self`
+    pub fn contains(&self, other: Weekdays) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ::std::ops::BitOr for Weekdays {
+    type Output = Weekdays;
+    fn bitor(self, other: Weekdays) -> Weekdays {
+        Weekdays(self.0 | other.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A [time pattern](https://developers.meethue.com/documentation/datatypes-and-time-patterns#16_time_patterns)
+/// as used by schedules and rules.
+///
+/// It can be parsed from and formatted to the string representation the bridge uses.
+/// Time intervals can be used with rule conditions on `/config/localtime`:
+///
+/// ```
+/// use philipshue::hue::{Condition, Operator, TimePattern};
+///
+/// let evening: TimePattern = "T18:00:00/T23:00:00".parse().unwrap();
+/// let condition = Condition::new("/config/localtime", Operator::In, evening);
+/// assert_eq!(condition.value.unwrap(), "T18:00:00/T23:00:00");
+/// ```
+pub enum TimePattern {
+    /// A single point in time, `YYYY-MM-DDThh:mm:ss`, optionally randomized with `A[hh]:[mm]:[ss]`
+    Absolute {
+        #[allow(missing_docs)]
+        date: Date,
+        #[allow(missing_docs)]
+        time: Time,
+        /// Random time added to the time, up to this value
+        random: Option<Time>,
+    },
+    /// A time on the given days of every week, `W[bbb]/T[hh]:[mm]:[ss]`, optionally randomized
+    Recurring {
+        #[allow(missing_docs)]
+        weekdays: Weekdays,
+        #[allow(missing_docs)]
+        time: Time,
+        /// Random time added to the time, up to this value
+        random: Option<Time>,
+    },
+    /// A time interval, `T[hh]:[mm]:[ss]/T[hh]:[mm]:[ss]`, optionally only on the given days
+    Interval {
+        /// The days the interval applies to or `None` for every day
+        weekdays: Option<Weekdays>,
+        #[allow(missing_docs)]
+        start: Time,
+        #[allow(missing_docs)]
+        end: Time,
+    },
+    /// A timer that expires after the duration, `PT[hh]:[mm]:[ss]`, optionally randomized
+    Timer {
+        #[allow(missing_docs)]
+        duration: Time,
+        /// Random time added to the duration, up to this value
+        random: Option<Time>,
+    },
+    /// A timer that restarts after it expires, `R[nn]/PT[hh]:[mm]:[ss]`, optionally randomized
+    RecurringTimer {
+        /// How many times the timer runs, from 1 to 99, or `None` to run forever
+        recurrences: Option<u8>,
+        #[allow(missing_docs)]
+        duration: Time,
+        /// Random time added to the duration, up to this value
+        random: Option<Time>,
+    },
+    /// A pattern received from the bridge that couldn't be parsed, kept as is
+    Other(String),
+}
+
+fn invalid_pattern<P: Into<String>>(pattern: P, reason: &str) -> ::errors::HueError {
+    ::errors::HueErrorKind::InvalidTimePattern(pattern.into(), reason.to_owned()).into()
+}
+
+impl TimePattern {
+    /// A timer that expires once after the given duration
+    pub fn timer(d: Duration) -> ::errors::Result<Self> {
+        Time::from_duration(d).map(|duration| TimePattern::Timer { duration, random: None })
+    }
+    /// Checks that all values of the pattern are in range
+    pub fn validate(&self) -> ::errors::Result<()> {
+        use self::TimePattern::*;
+
+        let times = match *self {
+            Absolute { date, time, random } => {
+                Date::new(date.year, date.month, date.day)?;
+                [Some(time), random]
+            }
+            Recurring { weekdays, time, random } => {
+                Weekdays::from_bits(weekdays.0)?;
+                [Some(time), random]
+            }
+            Interval { weekdays, start, end } => {
+                if let Some(w) = weekdays {
+                    Weekdays::from_bits(w.0)?;
+                }
+                [Some(start), Some(end)]
+            }
+            Timer { duration, random } => [Some(duration), random],
+            RecurringTimer { recurrences, duration, random } => {
+                if let Some(n) = recurrences {
+                    if n == 0 || n > 99 {
+                        return Err(invalid_pattern(self.to_string(), "recurrences have to be between 1 and 99"));
+                    }
+                }
+                [Some(duration), random]
+            }
+            Other(ref pattern) => return Err(invalid_pattern(pattern.clone(), "unknown time pattern")),
+        };
+        for t in times.iter().filter_map(|t| *t) {
+            Time::new(t.hour, t.minute, t.second)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TimePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::TimePattern::*;
+
+        let random = match *self {
+            Absolute { date, time, random } => {
+                write!(f, "{}T{}", date, time)?;
+                random
+            }
+            Recurring { weekdays, time, random } => {
+                write!(f, "W{:03}/T{}", weekdays.0, time)?;
+                random
+            }
+            Interval { weekdays, start, end } => {
+                if let Some(w) = weekdays {
+                    write!(f, "W{:03}/", w.0)?;
+                }
+                write!(f, "T{}/T{}", start, end)?;
+                None
+            }
+            Timer { duration, random } => {
+                write!(f, "PT{}", duration)?;
+                random
+            }
+            RecurringTimer { recurrences, duration, random } => {
+                match recurrences {
+                    Some(n) => write!(f, "R{:02}/PT{}", n, duration)?,
+                    None => write!(f, "R/PT{}", duration)?,
+                }
+                random
+            }
+            Other(ref pattern) => return f.write_str(pattern),
+        };
+        match random {
+            Some(r) => write!(f, "A{}", r),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A small cursor over a time pattern string
+struct PatternParser<'a> {
+    pattern: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> PatternParser<'a> {
+    fn error(&self, reason: &str) -> ::errors::HueError {
+        invalid_pattern(self.pattern, reason)
+    }
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest.starts_with(prefix) {
+            self.rest = &self.rest[prefix.len()..];
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, prefix: &str) -> ::errors::Result<()> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", prefix)))
+        }
+    }
+    fn number<N: FromStr>(&mut self, max_digits: usize) -> ::errors::Result<Option<N>> {
+        let len = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return Ok(None);
+        }
+        if len > max_digits {
+            return Err(self.error("number has too many digits"));
+        }
+        let (n, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        n.parse().map(Some).map_err(|_| self.error("number out of range"))
+    }
+    fn digits<N: FromStr>(&mut self, count: usize) -> ::errors::Result<N> {
+        let len = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        if len != count {
+            return Err(self.error(&format!("expected {} digits", count)));
+        }
+        self.number(count).map(Option::unwrap)
+    }
+    fn time(&mut self) -> ::errors::Result<Time> {
+        let hour = self.digits(2)?;
+        self.expect(":")?;
+        let minute = self.digits(2)?;
+        self.expect(":")?;
+        let second = self.digits(2)?;
+        Time::new(hour, minute, second)
+    }
+    fn random(&mut self) -> ::errors::Result<Option<Time>> {
+        if self.eat("A") {
+            self.time().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+    fn weekdays(&mut self) -> ::errors::Result<Weekdays> {
+        let bits = self.number(3)?.ok_or_else(|| self.error("expected a weekday bitmask"))?;
+        Weekdays::from_bits(bits)
+    }
+    fn finish(self, pattern: TimePattern) -> ::errors::Result<TimePattern> {
+        if self.rest.is_empty() {
+            Ok(pattern)
+        } else {
+            Err(self.error("unexpected trailing characters"))
+        }
+    }
+}
+
+use std::str::FromStr;
+use std::time::Duration;
+
+impl FromStr for TimePattern {
+    type Err = ::errors::HueError;
+
+    fn from_str(s: &str) -> ::errors::Result<Self> {
+        let mut p = PatternParser { pattern: s, rest: s };
+
+        let pattern = if p.eat("R") {
+            let recurrences = p.number(2)?;
+            if recurrences == Some(0) {
+                return Err(p.error("recurrences have to be between 1 and 99"));
+            }
+            p.expect("/PT")?;
+            TimePattern::RecurringTimer {
+                recurrences,
+                duration: p.time()?,
+                random: p.random()?,
+            }
+        } else if p.eat("PT") {
+            TimePattern::Timer {
+                duration: p.time()?,
+                random: p.random()?,
+            }
+        } else if p.eat("W") {
+            let weekdays = p.weekdays()?;
+            p.expect("/T")?;
+            let time = p.time()?;
+            if p.eat("/T") {
+                TimePattern::Interval {
+                    weekdays: Some(weekdays),
+                    start: time,
+                    end: p.time()?,
+                }
+            } else {
+                TimePattern::Recurring {
+                    weekdays,
+                    time,
+                    random: p.random()?,
+                }
+            }
+        } else if p.eat("T") {
+            let start = p.time()?;
+            p.expect("/T")?;
+            TimePattern::Interval {
+                weekdays: None,
+                start,
+                end: p.time()?,
+            }
+        } else {
+            let year = p.digits(4)?;
+            p.expect("-")?;
+            let month = p.digits(2)?;
+            p.expect("-")?;
+            let day = p.digits(2)?;
+            let date = Date::new(year, month, day)?;
+            p.expect("T")?;
+            TimePattern::Absolute {
+                date,
+                time: p.time()?,
+                random: p.random()?,
+            }
+        };
+        p.finish(pattern)
+    }
+}
+
+impl ::serde::Serialize for TimePattern {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(s.parse().unwrap_or(TimePattern::Other(s)))
+    }
+}
+
+#[test]
+fn time_patterns() {
+    let valid = [
+        "2026-10-17T07:00:00",
+        "2026-10-17T07:00:00A00:30:00",
+        "W124/T07:00:00",
+        "W003/T09:30:00A00:15:00",
+        "T18:00:00/T23:00:00",
+        "W127/T22:00:00/T06:00:00",
+        "PT00:10:00",
+        "PT00:00:30A00:00:10",
+        "R05/PT00:01:00",
+        "R/PT00:01:00",
+    ];
+    for s in valid.iter() {
+        let p: TimePattern = s.parse().unwrap();
+        assert_eq!(p.to_string(), *s);
+        assert!(p.validate().is_ok());
+    }
+
+    let invalid = [
+        "",
+        "2026-02-30T07:00:00",
+        "2026-10-17T24:00:00",
+        "W000/T07:00:00",
+        "W128/T07:00:00",
+        "W124T07:00:00",
+        "PT00:60:00",
+        "R00/PT00:01:00",
+        "R100/PT00:01:00",
+        "PT00:10:00B",
+        "T7:00:00/T08:00:00",
+    ];
+    for s in invalid.iter() {
+        assert!(s.parse::<TimePattern>().is_err(), "{:?} should be invalid", s);
+    }
+
+    let p: TimePattern = ::serde_json::from_str(r#""W124/T07:00:00/T08:00:00A00:10:00""#).unwrap();
+    assert_eq!(p, TimePattern::Other("W124/T07:00:00/T08:00:00A00:10:00".to_owned()));
+    assert_eq!(::serde_json::to_string(&p).unwrap(), r#""W124/T07:00:00/T08:00:00A00:10:00""#);
+    assert!(p.validate().is_err());
+
+    assert_eq!(TimePattern::timer(Duration::from_secs(3725)).unwrap().to_string(), "PT01:02:05");
+    assert!(TimePattern::timer(Duration::from_secs(24 * 3600)).is_err());
+    assert_eq!((Weekdays::SATURDAY | Weekdays::SUNDAY), Weekdays::WEEKEND);
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// A [resourcelink](https://developers.meethue.com/documentation/resourcelinks-api)
+///
+/// Resourcelinks group resources (scenes, rules, schedules, sensors, ...) that belong together,
+/// so that they can be managed as a whole.
+pub struct ResourceLink {
+    /// Human readable name of the resourcelink
+    pub name: String,
+    /// Description of the resourcelink
+    #[serde(default)]
+    pub description: String,
+    /// Type of the resourcelink. This is always "Link".
+    #[serde(rename="type")]
+    pub link_type: String,
+    /// Application specific identifier of the kind of resourcelink
+    pub classid: u16,
+    /// The name of the user that created the resourcelink
+    pub owner: String,
+    /// Whether the resourcelink can be deleted automatically by the bridge
+    #[serde(default)]
+    pub recycle: bool,
+    /// Addresses of the linked resources, e.g. "/scenes/ab1f5a2" or "/rules/3"
+    pub links: Vec<String>,
+}
+
+impl ResourceLink {
+    /// The linked resources as typed references
+    pub fn resources(&self) -> Vec<ResourceRef> {
+        self.links.iter().map(|l| ResourceRef::from_address(l)).collect()
+    }
+    /// Whether the resourcelink links to the given resource
+    pub fn links_to(&self, resource: &ResourceRef) -> bool {
+        self.resources().contains(resource)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A reference to a resource on the bridge, as used in the links of a `ResourceLink`
+pub enum ResourceRef {
+    #[allow(missing_docs)]
+    Light(LightId),
+    #[allow(missing_docs)]
+    Group(GroupId),
+    #[allow(missing_docs)]
+    Scene(SceneId),
+    #[allow(missing_docs)]
+    Sensor(SensorId),
+    #[allow(missing_docs)]
+    Rule(RuleId),
+    #[allow(missing_docs)]
+    Schedule(ScheduleId),
+    #[allow(missing_docs)]
+    ResourceLink(ResourceLinkId),
+    /// An address that doesn't refer to a known kind of resource
+    Other(String),
+}
+
+impl ResourceRef {
+    /// Parses an address such as "/lights/1"
+    pub fn from_address(address: &str) -> Self {
+        use self::ResourceRef::*;
+
+        let mut parts = address.trim_start_matches('/').split('/');
+        let (kind, id) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(kind), Some(id), None) if !id.is_empty() => (kind, id),
+            _ => return Other(address.to_owned()),
+        };
+        let r = match kind {
+            "lights" => id.parse().map(Light),
+            "groups" => id.parse().map(Group),
+            "scenes" => return Scene(id.into()),
+            "sensors" => id.parse().map(Sensor),
+            "rules" => id.parse().map(Rule),
+            "schedules" => id.parse().map(Schedule),
+            "resourcelinks" => id.parse().map(ResourceLink),
+            _ => return Other(address.to_owned()),
+        };
+        r.unwrap_or_else(|_| Other(address.to_owned()))
+    }
+    /// The address of the resource, e.g. "/lights/1"
+    pub fn address(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for ResourceRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ResourceRef::*;
+        match *self {
+            Light(ref id) => write!(f, "/lights/{}", id),
+            Group(ref id) => write!(f, "/groups/{}", id),
+            Scene(ref id) => write!(f, "/scenes/{}", id),
+            Sensor(ref id) => write!(f, "/sensors/{}", id),
+            Rule(ref id) => write!(f, "/rules/{}", id),
+            Schedule(ref id) => write!(f, "/schedules/{}", id),
+            ResourceLink(ref id) => write!(f, "/resourcelinks/{}", id),
+            Other(ref address) => address.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The address of an attribute of a resource, such as "/lights/1/state/bri"
+pub struct ResourcePath {
+    /// The resource. Addresses without an ID, such as "/config", are `ResourceRef::Other`.
+    pub resource: ResourceRef,
+    /// The names leading from the resource to the attribute, such as `["state", "bri"]`
+    pub attribute: Vec<String>,
+}
+
+impl ResourcePath {
+    /// Parses an address such as "/lights/1/state/bri" or "/config/name"
+    pub fn from_address(address: &str) -> Self {
+        let mut parts = address.split('/').filter(|p| !p.is_empty()).map(ToOwned::to_owned);
+        let kind = parts.next().unwrap_or_default();
+        let rest: Vec<String> = parts.collect();
+        if let Some(id) = rest.first() {
+            let resource = ResourceRef::from_address(&format!("/{}/{}", kind, id));
+            if !matches!(resource, ResourceRef::Other(_)) {
+                return ResourcePath {
+                    resource,
+                    attribute: rest[1..].to_vec(),
+                };
+            }
+        }
+        ResourcePath {
+            resource: ResourceRef::Other(format!("/{}", kind)),
+            attribute: rest,
+        }
+    }
+    /// The address of the attribute
+    pub fn address(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for ResourcePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.resource.fmt(f)?;
+        for name in &self.attribute {
+            write!(f, "/{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An attribute changed by a command, as reported by the bridge
+pub struct Change {
+    /// The attribute that was changed
+    pub path: ResourcePath,
+    /// The new value of the attribute, or the amount it was changed by for increments like `bri_inc`
+    pub value: JsonValue,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The attributes changed by a command, in the order the bridge reported them
+pub struct ChangeSet {
+    /// The changes
+    pub changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    /// Parses the objects the bridge responds with, such as `{"/lights/1/state/bri": 200}`
+    pub fn from_successes(successes: Vec<JsonMap<String, JsonValue>>) -> Self {
+        successes.into_iter()
+            .flat_map(|s| s.into_iter())
+            .map(|(address, value)| Change {
+                path: ResourcePath::from_address(&address),
+                value,
+            })
+            .collect()
+    }
+    /// Iterates over the changes
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Change> {
+        self.changes.iter()
+    }
+    /// The number of changes
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+    /// Whether nothing was changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+    /// The value the attribute at `address`, such as "/lights/1/state/bri", was last changed to
+    pub fn get(&self, address: &str) -> Option<&JsonValue> {
+        let path = ResourcePath::from_address(address);
+        self.iter().rev().find(|c| c.path == path).map(|c| &c.value)
+    }
+    /// The attributes changed below `prefix` of the resource, relative to `prefix`
+    fn below<'a>(&'a self, resource: &'a ResourceRef, prefix: &'a [&str])
+        -> impl Iterator<Item = (&'a [String], &'a JsonValue)> + 'a {
+        self.iter()
+            .filter(move |c| c.path.resource == *resource && c.path.attribute.len() > prefix.len() &&
+                             c.path.attribute.iter().zip(prefix).all(|(a, p)| a == p))
+            .map(move |c| (&c.path.attribute[prefix.len()..], &c.value))
+    }
+    /// Updates the state of the light `id` with the changes to it
+    pub fn apply_to_light_state(&self, id: LightId, state: &mut LightState) -> ::errors::Result<()> {
+        apply_changes(state, self.below(&ResourceRef::Light(id), &["state"]))
+    }
+    /// Updates the group `id` with the changes to its attributes and action
+    pub fn apply_to_group(&self, id: GroupId, group: &mut Group) -> ::errors::Result<()> {
+        let resource = ResourceRef::Group(id);
+        if group.action.is_none() && self.below(&resource, &["action"]).next().is_some() {
+            group.action = Some(LightStateChange::default());
+        }
+        apply_changes(group, self.below(&resource, &[]))
+    }
+    /// Updates the configuration with the changes to it
+    pub fn apply_to_configuration(&self, config: &mut Configuration) -> ::errors::Result<()> {
+        apply_changes(config, self.below(&ResourceRef::Other("/config".to_owned()), &[]))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An attribute a command couldn't change
+pub struct AttributeError {
+    /// The attribute that couldn't be changed
+    pub path: ResourcePath,
+    /// Why it couldn't be changed
+    pub error: ::errors::BridgeError,
+    /// The description the bridge gave
+    pub description: String,
+}
+
+impl From<AttributeError> for ::errors::HueError {
+    fn from(e: AttributeError) -> Self {
+        ::errors::HueErrorKind::BridgeError {
+                address: e.path.address(),
+                description: e.description,
+                error: e.error,
+            }
+            .into()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The response to a command, whose attributes the bridge changes independently of each other.
+///
+/// Some attributes of a command can fail while others are changed, for example when a light
+/// is turned on and given a colour temperature it doesn't support.
+pub struct CommandResponse {
+    /// The attributes that were changed
+    pub changes: ChangeSet,
+    /// The attributes that couldn't be changed
+    pub errors: Vec<AttributeError>,
+}
+
+impl CommandResponse {
+    /// Whether every attribute of the command was changed
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+    /// The names of the attributes that couldn't be changed, such as "ct".
+    /// Errors about a whole resource, such as a light that doesn't exist, are left out.
+    pub fn failed_attributes(&self) -> Vec<&str> {
+        self.errors.iter()
+            .filter(|e| match e.path.resource {
+                ResourceRef::Other(ref address) => address == "/config",
+                _ => true,
+            })
+            .filter_map(|e| match e.path.attribute[..] {
+                [ref parent] if parent == "state" || parent == "action" => None,
+                ref attribute => attribute.last(),
+            })
+            .map(|a| &**a)
+            .collect()
+    }
+    /// The changes, or the first attribute that couldn't be changed as an error
+    pub fn into_result(self) -> ::errors::Result<ChangeSet> {
+        match self.errors.into_iter().next() {
+            Some(e) => Err(e.into()),
+            None => Ok(self.changes),
+        }
+    }
+}
+
+impl ::std::iter::FromIterator<Change> for ChangeSet {
+    fn from_iter<I: IntoIterator<Item = Change>>(iter: I) -> Self {
+        ChangeSet { changes: iter.into_iter().collect() }
+    }
+}
+
+impl IntoIterator for ChangeSet {
+    type Item = Change;
+    type IntoIter = ::std::vec::IntoIter<Change>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ChangeSet {
+    type Item = &'a Change;
+    type IntoIter = ::std::slice::Iter<'a, Change>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+/// Sets the attributes in the JSON form of `target` like the bridge did
+fn apply_changes<'a, T, I>(target: &mut T, changes: I) -> ::errors::Result<()>
+    where T: ::serde::Serialize + ::serde::de::DeserializeOwned, I: Iterator<Item = (&'a [String], &'a JsonValue)>
+{
+    let mut json = ::serde_json::to_value(&*target)?;
+    for (attribute, value) in changes {
+        set_attribute(&mut json, attribute, value);
+    }
+    *target = ::serde_json::from_value(json)?;
+    Ok(())
+}
+
+fn set_attribute(json: &mut JsonValue, attribute: &[String], value: &JsonValue) {
+    let (name, parents) = match attribute.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    let object = parents.iter()
+        .try_fold(json, |json, parent| json.get_mut(parent))
+        .and_then(JsonValue::as_object_mut);
+    let object = match object {
+        Some(object) => object,
+        None => return,
+    };
+    if name == "transitiontime" || name == "scene" {
+        return;
+    }
+
+    let (name, value) = match name.strip_suffix("_inc") {
+        Some(name) => match increment(name, object.get(name), value) {
+            Some(value) => (name, value),
+            None => return,
+        },
+        None => (&**name, value.clone()),
+    };
+    let colormode = match name {
+        "xy" => Some("xy"),
+        "ct" => Some("ct"),
+        "hue" | "sat" => Some("hs"),
+        _ => None,
+    };
+    if let Some(mode) = colormode {
+        object.insert("colormode".to_owned(), mode.into());
+    }
+    object.insert(name.to_owned(), value);
+}
+
+/// Adds an increment to the current value of an attribute, keeping it in the range of the attribute
+fn increment(name: &str, current: Option<&JsonValue>, inc: &JsonValue) -> Option<JsonValue> {
+    let current = current?;
+    if name == "xy" {
+        let (x, y) = (current.get(0)?.as_f64()? + inc.get(0)?.as_f64()?, current.get(1)?.as_f64()? + inc.get(1)?.as_f64()?);
+        let (min, max) = attribute_range("xy")?;
+        return Some(vec![x.clamp(min, max), y.clamp(min, max)].into());
+    }
+    let value = current.as_i64()? + inc.as_i64()?;
+    Some(match attribute_range(name) {
+        Some((_, max)) if name == "hue" => value.rem_euclid(max as i64 + 1),
+        Some((min, max)) => value.clamp(min as i64, max as i64),
+        None => value,
+    }.into())
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// A resourcelink to be created on the bridge
+pub struct ResourceLinkCreater {
+    /// Human readable name of the resourcelink
+    pub name: String,
+    /// Description of the resourcelink
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Application specific identifier of the kind of resourcelink
+    pub classid: u16,
+    /// Whether the bridge can just delete this resourcelink.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recycle: Option<bool>,
+    /// Addresses of the linked resources. See `ResourceRef::address()`.
+    pub links: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+/// Struct for modifying a resourcelink. Only the fields that are set will be changed.
+pub struct ResourceLinkModifier {
+    /// Name to rename the resourcelink to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The new description of the resourcelink
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The new class ID of the resourcelink
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classid: Option<u16>,
+    /// The new addresses of the linked resources. These replace all existing links.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<String>>,
+}
+
+#[test]
+fn resource_refs() {
+    let addresses = ["/lights/1", "/groups/0", "/scenes/ab1f5a2", "/sensors/12", "/rules/3",
+                     "/schedules/4", "/resourcelinks/5", "/config/localtime", "/lights/new"];
+    for a in addresses.iter() {
+        assert_eq!(ResourceRef::from_address(a).address(), *a);
+    }
+    assert_eq!(ResourceRef::from_address("/scenes/ab1f5a2"), ResourceRef::Scene("ab1f5a2".into()));
+    assert_eq!(ResourceRef::from_address("/groups/0"), ResourceRef::Group(GroupId::ALL));
+    assert_eq!(ResourceRef::from_address("/lights/new"), ResourceRef::Other("/lights/new".to_owned()));
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+/// How many more resources of a kind can be created on the bridge
+pub struct Capacity {
+    /// How many more can be created
+    pub available: usize,
+    /// How many there can be in total. Not reported for every kind of resource.
+    #[serde(default)]
+    pub total: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+/// Capacity of sensors, in total and per kind of sensor
+pub struct SensorCapacity {
+    /// How many more sensors can be created
+    pub available: usize,
+    /// How many sensors there can be in total
+    #[serde(default)]
+    pub total: Option<usize>,
+    /// Capacity of CLIP sensors
+    #[serde(default)]
+    pub clip: Option<Capacity>,
+    /// Capacity of ZLL sensors
+    #[serde(default)]
+    pub zll: Option<Capacity>,
+    /// Capacity of ZGP sensors
+    #[serde(default)]
+    pub zgp: Option<Capacity>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+/// Capacity of scenes and the light states stored in them
+pub struct SceneCapacity {
+    /// How many more scenes can be created
+    pub available: usize,
+    /// How many scenes there can be in total
+    #[serde(default)]
+    pub total: Option<usize>,
+    /// Capacity of light states shared by all scenes
+    pub lightstates: Capacity,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+/// Capacity of rules and the conditions and actions in them
+pub struct RuleCapacity {
+    /// How many more rules can be created
+    pub available: usize,
+    /// How many rules there can be in total
+    #[serde(default)]
+    pub total: Option<usize>,
+    /// Capacity of conditions shared by all rules
+    pub conditions: Capacity,
+    /// Capacity of actions shared by all rules
+    pub actions: Capacity,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+/// Timezones supported by the bridge
+pub struct Timezones {
+    /// The supported timezones as OlsenIDs (e.g. "Europe/Amsterdam")
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// The [capabilities](https://developers.meethue.com/documentation/capabilities-api) of the bridge
+///
+/// Tells how many more resources of every kind can be created.
+pub struct Capabilities {
+    #[allow(missing_docs)]
+    pub lights: Capacity,
+    #[allow(missing_docs)]
+    pub sensors: SensorCapacity,
+    #[allow(missing_docs)]
+    pub groups: Capacity,
+    #[allow(missing_docs)]
+    pub scenes: SceneCapacity,
+    #[allow(missing_docs)]
+    pub rules: RuleCapacity,
+    #[allow(missing_docs)]
+    pub schedules: Capacity,
+    #[allow(missing_docs)]
+    pub resourcelinks: Capacity,
+    /// The timezones that can be set in the configuration
+    #[serde(default)]
+    pub timezones: Timezones,
+}
+
+#[test]
+fn capabilities() {
+    let c: Capabilities = ::serde_json::from_str(r#"{
+        "lights": {"available": 10, "total": 63},
+        "sensors": {"available": 60, "total": 250, "clip": {"available": 60, "total": 250},
+                    "zll": {"available": 60, "total": 64}, "zgp": {"available": 60, "total": 64}},
+        "groups": {"available": 60, "total": 64},
+        "scenes": {"available": 100, "total": 200, "lightstates": {"available": 1500, "total": 12600}},
+        "rules": {"available": 150, "total": 250, "conditions": {"available": 1500, "total": 1500},
+                  "actions": {"available": 1000, "total": 1000}},
+        "schedules": {"available": 100, "total": 100},
+        "resourcelinks": {"available": 64, "total": 64},
+        "streaming": {"available": 1, "total": 1, "channels": 10},
+        "timezones": {"values": ["Africa/Abidjan", "Europe/Amsterdam"]}
+    }"#).unwrap();
+    assert_eq!(c.lights.total, Some(63));
+    assert_eq!(c.sensors.clip.unwrap().available, 60);
+    assert_eq!(c.scenes.lightstates.available, 1500);
+    assert_eq!(c.rules.actions.available, 1000);
+    assert_eq!(c.timezones.values[1], "Europe/Amsterdam");
+
+    let c: Capabilities = ::serde_json::from_str(r#"{
+        "lights": {"available": 0}, "sensors": {"available": 0}, "groups": {"available": 0},
+        "scenes": {"available": 0, "lightstates": {"available": 0}},
+        "rules": {"available": 0, "conditions": {"available": 0}, "actions": {"available": 0}},
+        "schedules": {"available": 0}, "resourcelinks": {"available": 0}
+    }"#).unwrap();
+    assert_eq!(c.groups.total, None);
+    assert!(c.sensors.clip.is_none());
+    assert!(c.timezones.values.is_empty());
+}
+
+#[test]
+fn new_lights() {
+    let n: NewLights = ::serde_json::from_str(r#"{"7": {"name": "Hue Lamp 7"}, "8": {"name": "Hue Lamp 8"}, "lastscan": "2012-10-29T12:00:00"}"#).unwrap();
+    assert_eq!(n.lastscan, LastScan::Finished("2012-10-29T12:00:00Z".parse().unwrap()));
+    assert_eq!(n.lights[&LightId(8)].name, "Hue Lamp 8");
+    let n: NewLights = ::serde_json::from_str(r#"{"lastscan": "active"}"#).unwrap();
+    assert_eq!(n.lastscan, LastScan::Active);
+    assert!(n.lights.is_empty());
+}
+
+#[test]
+fn transition_times() {
+    assert_eq!(to_deciseconds(Duration::from_millis(400)).unwrap(), 4);
+    assert_eq!(to_deciseconds(Duration::from_millis(1249)).unwrap(), 12);
+    assert_eq!(to_deciseconds(Duration::from_millis(6_553_500)).unwrap(), 65535);
+    assert!(to_deciseconds(Duration::from_secs(6554)).is_err());
+
+    let cmd = LightCommand::default().on().with_transitiontime(Duration::from_secs(0));
+    assert_eq!(::serde_json::to_string(&cmd).unwrap(), r#"{"on":true,"transitiontime":0}"#);
+    let cmd = LightCommand::default().with_transitiontime(Duration::from_secs(7200));
+    assert!(::serde_json::to_string(&cmd).is_err());
+    let state: LightStateChange = ::serde_json::from_str(r#"{"on":true,"transitiontime":15}"#).unwrap();
+    assert_eq!(state.transitiontime, Some(Duration::from_millis(1500)));
+}
+
+#[test]
+fn light_enums() {
+    let state: LightStateChange = ::serde_json::from_str(r#"{"alert":"lselect","effect":"prism","colormode":"xy"}"#).unwrap();
+    assert_eq!(state.alert, Some(Alert::LSelect));
+    assert_eq!(state.effect, Some(Effect::Unknown("prism".to_owned())));
+    assert_eq!(state.colormode, Some(ColorMode::Xy));
+
+    let cmd = LightCommand::default().with_alert(Alert::Select).with_effect(Effect::ColorLoop);
+    assert_eq!(::serde_json::to_string(&cmd).unwrap(), r#"{"alert":"select","effect":"colorloop"}"#);
+}
+
+#[test]
+fn ids() {
+    let lights: Vec<LightId> = ::serde_json::from_str(r#"["1", 2]"#).unwrap();
+    assert_eq!(lights, vec![LightId(1), LightId(2)]);
+    let g = GroupCommand { name: None, lights, class: None };
+    assert_eq!(::serde_json::to_string(&g).unwrap(), r#"{"lights":["1","2"]}"#);
+
+    let scenes: BTreeMap<SceneId, LightId> = ::serde_json::from_str(r#"{"ab1f5a2": 3}"#).unwrap();
+    assert_eq!(scenes[&SceneId::from("ab1f5a2")], LightId(3));
+    assert_eq!(GroupId::ALL.to_string(), "0");
+    assert!("abc".parse::<SensorId>().is_err());
+}
+
+#[test]
+fn rules() {
+    let rules: BTreeMap<RuleId, Rule> = ::serde_json::from_str(r#"{
+        "1": {
+            "name": "Wall Switch Rule",
+            "owner": "78H56B12BA",
+            "created": "2014-06-06T12:38:13",
+            "lasttriggered": "none",
+            "timestriggered": 0,
+            "status": "enabled",
+            "conditions": [
+                {"address": "/sensors/2/state/buttonevent", "operator": "eq", "value": "16"},
+                {"address": "/sensors/2/state/lastupdated", "operator": "dx"},
+                {"address": "/sensors/5/state/presence", "operator": "stable", "value": "PT00:15:00"},
+                {"address": "/config/localtime", "operator": "in", "value": "T20:00:00/T08:00:00"}
+            ],
+            "actions": [{"address": "/groups/0/action", "method": "PUT", "body": {"scene": "S3"}}]
+        },
+        "2": {
+            "name": "Gone",
+            "owner": "78H56B12BA",
+            "created": "2014-06-06T12:38:13",
+            "lasttriggered": "2014-06-07T08:00:00",
+            "timestriggered": 3,
+            "status": "resourcedeleted",
+            "recycle": true,
+            "conditions": [{"address": "/sensors/7/state/flag", "operator": "not stable", "value": "PT00:00:10"}],
+            "actions": [{"address": "/sensors/7/state", "method": "PUT", "body": {"flag": false}}]
+        }
+    }"#).unwrap();
+    let rule = &rules[&RuleId::from(1)];
+    assert_eq!(rule.status, RuleStatus::Enabled);
+    assert_eq!(rule.lasttriggered, None);
+    assert_eq!(rule.conditions[0], Condition::new("/sensors/2/state/buttonevent", Operator::Eq, 16));
+    assert_eq!(rule.conditions[1], Condition::changed("/sensors/2/state/lastupdated"));
+    assert_eq!(rule.conditions[2].operator, Operator::Stable);
+    assert_eq!(rule.conditions[3].value.as_ref().unwrap(), "T20:00:00/T08:00:00");
+    assert_eq!(rule.actions[0].body["scene"], "S3");
+
+    let rule = &rules[&RuleId::from(2)];
+    assert_eq!(rule.status, RuleStatus::ResourceDeleted);
+    assert_eq!(rule.timestriggered, 3);
+    assert!(rule.recycle);
+    assert_eq!(rule.conditions[0].operator, Operator::NotStable);
+    assert_eq!(rule.actions[0].method, ActionMethod::Put);
+}
+
+#[test]
+fn timestamps() {
+    let state: GroupState = ::serde_json::from_str(
+        r#"{"any_on":true,"all_on":false,"lastupdated":"2017-09-18T14:38:21","lastswitched":"none"}"#).unwrap();
+    assert_eq!(state.lastupdated, Some("2017-09-18T14:38:21Z".parse().unwrap()));
+    assert_eq!(state.lastswitched, None);
+    let json = ::serde_json::to_string(&state).unwrap();
+    assert_eq!(json, r#"{"any_on":true,"all_on":false,"lastupdated":"2017-09-18T14:38:21"}"#);
+
+    let user: WhitelistUser = ::serde_json::from_str(
+        r#"{"name":"my_hue_app#homepc","last use date":"2017-09-18T14:38:21.123","create date":"2017-01-01T00:00:00"}"#).unwrap();
+    assert!(user.last_use_date > user.create_date);
+    assert!(::serde_json::from_str::<WhitelistUser>(r#"{"name":"a","last use date":"yesterday","create date":"none"}"#).is_err());
+}
+
+#[test]
+fn validate_light_command() {
+    use errors::{HueError, HueErrorKind, Violation};
+
+    assert!(LightCommand::default().on().with_bri(254).with_ct(153).validate().is_ok());
+    assert!(LightCommand::default().with_hue_inc(-65534).with_ct_inc(65534).validate().is_ok());
+    assert!(LightCommand::default().with_hue_inc(65535).validate().is_err());
+    assert!(LightCommand::default().with_ct_inc(-65535).validate().is_err());
+    assert!(LightCommand::default().with_xy((f32::NAN, 0.3)).validate().is_err());
+    assert!(LightCommand::default().with_xy_inc((0.1, f32::NAN)).validate().is_err());
+    // Transition times are rounded to deciseconds before they are checked
+    let longest = Duration::from_millis(6_553_540);
+    assert!(LightCommand::default().with_transitiontime(longest).validate().is_ok());
+    assert!(LightCommand::default().with_transitiontime(longest + Duration::from_millis(20)).validate().is_err());
+
+    let cmd = LightCommand::default()
+        .with_bri(0)
+        .with_xy((1.2, 0.3))
+        .with_ct(600)
+        .with_hue(100)
+        .with_hue_inc(200)
+        .with_sat_inc(-300);
+    match cmd.validate() {
+        Err(HueError(HueErrorKind::InvalidLightCommand(v), _)) => {
+            let fields: Vec<_> = v.iter().map(|v| match *v {
+                Violation::OutOfRange { field, .. } => field,
+                Violation::Conflict(a, _) => a,
+            }).collect();
+            assert_eq!(fields, ["bri", "xy", "ct", "sat_inc", "hue"]);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn merge_light_commands() {
+    let old = LightCommand::default().on().with_bri(100).with_ct(300).with_hue_inc(10);
+    let merged = old.merge(&LightCommand::default().with_bri_inc(-30).with_transitiontime(Duration::from_secs(1)));
+    assert_eq!(merged.on, Some(true));
+    assert_eq!((merged.bri, merged.bri_inc), (Some(70), None));
+    assert_eq!((merged.ct, merged.hue_inc), (Some(300), Some(10)));
+    assert_eq!(merged.transitiontime, Some(Duration::from_secs(1)));
+
+    // A new colour replaces the old one, even if the bridge would prefer the old kind
+    let merged = merged.merge(&LightCommand::default().with_hue(200).with_sat(254).off());
+    assert_eq!((merged.ct, merged.hue, merged.hue_inc, merged.sat), (None, Some(200), None, Some(254)));
+    assert_eq!(merged.on, Some(false));
+
+    // A superseded flash isn't shown later
+    let merged = LightCommand::default().with_alert(Alert::Select).merge(&LightCommand::default().on());
+    assert!(merged.alert.is_none());
+
+    let merged = LightCommand::default().with_bri_inc(200).merge(&LightCommand::default().with_bri_inc(100));
+    assert_eq!(merged.bri_inc, Some(254));
+    assert!(merged.validate().is_ok());
+
+    // Increments out of range don't overflow
+    let max = LightCommand::default().with_bri_inc(i16::MAX).with_hue_inc(i32::MAX).with_ct_inc(i32::MIN);
+    let merged = max.merge(&max);
+    assert_eq!((merged.bri_inc, merged.hue_inc, merged.ct_inc), (Some(254), Some(65534), Some(-65534)));
+    let merged = LightCommand::default().with_bri(200).merge(&LightCommand::default().with_bri_inc(i16::MAX));
+    assert_eq!(merged.bri, Some(254));
+}
+
+#[test]
+fn change_sets() {
+    use serde_json::{from_str, from_value};
+
+    let successes = from_str(r#"[{"/lights/1/state/on":true},{"/lights/1/state/bri_inc":-40},{"/lights/1/state/xy":[0.3,0.3]},
+                                 {"/lights/2/state/bri":10},{"/groups/3/action/ct":300},{"/groups/3/name":"Kitchen"},
+                                 {"/config/name":"Home"},{"/config/swupdate/checkforupdate":true}]"#).unwrap();
+    let changes = ChangeSet::from_successes(successes);
+    assert_eq!(changes.len(), 8);
+    assert_eq!(changes.changes[0].path, ResourcePath {
+        resource: ResourceRef::Light(1.into()),
+        attribute: vec!["state".to_owned(), "on".to_owned()],
+    });
+    assert_eq!(changes.changes[6].path.address(), "/config/name");
+    assert_eq!(changes.get("/groups/3/name"), Some(&JsonValue::from("Kitchen")));
+
+    let mut state: LightState = from_str(r#"{"on":false,"bri":20,"xy":[0.5,0.4],"ct":400,"alert":"none",
+                                            "colormode":"ct","reachable":true}"#).unwrap();
+    changes.apply_to_light_state(1.into(), &mut state).unwrap();
+    assert!(state.on);
+    assert_eq!(state.bri, 1);
+    assert_eq!(state.xy, Some((0.3, 0.3)));
+    assert_eq!(state.colormode, Some(ColorMode::Xy));
+
+    let mut group: Group = from_str(r#"{"name":"Group","lights":["1"],"type":"Room"}"#).unwrap();
+    changes.apply_to_group(3.into(), &mut group).unwrap();
+    assert_eq!(group.name, "Kitchen");
+    assert_eq!(group.action.unwrap().ct, Some(300));
+
+    let mut config: Configuration = from_value(::serde_json::json!({
+        "name": "Bridge", "whitelist": {}, "apiversion": "1.16.0", "swversion": "1709131301",
+        "swupdate": {"checkforupdate": false, "devicetypes": {"bridge": false, "lights": []},
+                     "updatestate": 0, "url": "", "text": "", "notify": false},
+        "proxyaddress": "none", "proxyport": 0, "linkbutton": false, "ipaddress": "192.168.1.2",
+        "mac": "00:17:88:00:00:00", "netmask": "255.255.255.0", "gateway": "192.168.1.1", "dhcp": true,
+        "portalservices": false, "UTC": "2017-06-01T10:00:00", "localtime": "none", "timezone": "none",
+        "zigbeechannel": 15, "modelid": "BSB002", "bridgeid": "001788FFFE000000", "factorynew": false
+    })).unwrap();
+    changes.apply_to_configuration(&mut config).unwrap();
+    assert_eq!(config.name, "Home");
+    assert!(config.swupdate.checkforupdate);
+    assert_eq!(config.utc.to_string(), "2017-06-01 10:00:00 UTC");
+}
@@ -2,17 +2,34 @@
 /// A user object returned from the API
 pub struct User{
     /// The username of the user
-    pub username: String
+    pub username: ::hue::Username
 }
 
 #[derive(Debug, Deserialize)]
 /// An object containing the ID of something newly created
 pub struct Id<T>
+    where T: FromStr, for<'a> T: Deserialize<'a>
 {
     /// The ID
+    #[serde(deserialize_with = "string_or_value")]
     pub id: T
 }
 
+use std::str::FromStr;
+use serde::Deserializer;
+use serde::de::Error as DeError;
+use serde_json::Value;
+
+/// The bridge returns most IDs of newly created objects as strings, even numeric ones
+fn string_or_value<'de, T, D>(de: D) -> Result<T, D::Error>
+    where T: FromStr, for<'a> T: Deserialize<'a>, D: Deserializer<'de>
+{
+    match Value::deserialize(de)? {
+        Value::String(s) => s.parse().map_err(|_| D::Error::custom(format!("invalid id {:?}", s))),
+        v => T::deserialize(v).map_err(D::Error::custom),
+    }
+}
+
 use ::serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -40,7 +57,7 @@ impl<'de, T> HueResponse<T> where T: Deserialize<'de> {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct SceneRecall<'a> {
-    pub scene: &'a str
+    pub scene: &'a ::hue::SceneId
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,3 +71,15 @@ pub struct Error {
     #[serde(rename="type")]
     pub code: u16,
 }
+
+#[test]
+fn created_ids() {
+    use serde_json::from_str;
+
+    let r: HueResponse<Id<usize>> = from_str(r#"{"success":{"id":"4"}}"#).unwrap();
+    assert_eq!(r.into_result().unwrap().id, 4);
+    let r: HueResponse<Id<usize>> = from_str(r#"{"success":{"id":7}}"#).unwrap();
+    assert_eq!(r.into_result().unwrap().id, 7);
+    let r: HueResponse<Id<String>> = from_str(r#"{"success":{"id":"ab1f5a2"}}"#).unwrap();
+    assert_eq!(r.into_result().unwrap().id, "ab1f5a2");
+}
@@ -10,12 +10,17 @@ extern crate ssdp;
 extern crate serde;
 extern crate serde_json;
 extern crate hyper;
-#[cfg(feature = "nupnp")]
+#[cfg(any(feature = "nupnp", feature = "https"))]
 extern crate hyper_openssl;
 #[macro_use]
 extern crate error_chain;
+extern crate chrono;
+#[cfg(feature = "timezones")]
+extern crate chrono_tz;
 
 pub use bridge::Bridge;
+#[cfg(feature = "async")]
+pub use async_bridge::AsyncBridge;
 #[cfg(feature = "nupnp")]
 pub use bridge::discover;
 #[cfg(feature = "upnp")]
@@ -26,6 +31,22 @@ pub use hue::LightCommand;
 pub mod errors;
 /// Handles all the communication with the bridge
 pub mod bridge;
+/// The HTTP transports the bridge communicates through
+pub mod transport;
+/// Limiting how fast commands are sent to the lights
+pub mod rate_limit;
+/// Sending only the newest command to each light in the background
+pub mod dispatcher;
+/// Recording requests to the bridge and replaying them in tests
+pub mod fixture;
+/// A non-blocking version of the bridge returning futures
+#[cfg(feature = "async")]
+pub mod async_bridge;
+/// An in-process mock bridge for tests
+#[cfg(feature = "mock")]
+pub mod mock;
 /// Structs mapping the different JSON-objects used with Hue API
 pub mod hue;
+/// Conversions between sRGB and the [CIE xy colour space](http://www.developers.meethue.com/documentation/color-conversions-rgb-xy) of the lights
+pub mod color;
 mod json;
@@ -0,0 +1,835 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use hyper::header::ContentType;
+use hyper::method::Method as HyperMethod;
+use hyper::net::Fresh;
+use hyper::server::{Server, Handler, Listening, Request, Response};
+use hyper::uri::RequestUri;
+use serde_json::{from_slice, from_value, to_vec};
+
+use errors::Result;
+use transport::Method;
+use ::hue::{attribute_range, FullState, JsonMap, JsonValue};
+
+/// How long registering users is allowed after the link button is pressed
+const LINK_BUTTON_WINDOW: Duration = Duration::from_secs(30);
+
+type Object = JsonMap<String, JsonValue>;
+
+/// A fake bridge serving the v1 API on a local port, for tests.
+///
+/// It keeps its state in memory in the shape of a `FullState` and implements lights,
+/// groups (including group 0), scenes, the configuration and the whitelist. Other resources
+/// can be read, but not changed. Connect to it with `Bridge::new(mock.ip(), username)`.
+///
+/// The server keeps running in the background until the process exits, even after the
+/// `MockBridge` is dropped.
+pub struct MockBridge {
+    state: Arc<Mutex<State>>,
+    listening: Listening,
+}
+
+impl MockBridge {
+    /// Starts a mock bridge with a colour light, a white ambiance light and no users
+    pub fn start() -> Result<Self> {
+        MockBridge::with_state(default_lights())
+    }
+    /// Starts a mock bridge with the given state, shaped like the full state returned by the bridge.
+    ///
+    /// Missing resources start empty, and a missing configuration is replaced by a default one.
+    pub fn with_state(state: JsonValue) -> Result<Self> {
+        let mut full = match state {
+            JsonValue::Object(full) => full,
+            _ => return Err("the state of a mock bridge has to be a JSON object".into()),
+        };
+        for key in ["lights", "groups", "scenes", "schedules", "sensors", "rules", "resourcelinks"].iter() {
+            full.entry(key.to_string()).or_insert_with(|| JsonValue::Object(Object::new()));
+        }
+        full.entry("config").or_insert_with(default_config);
+
+        let state = Arc::new(Mutex::new(State {
+            full,
+            link_pressed: None,
+            group0: default_action(),
+            created: 0,
+        }));
+        let listening = Server::http("127.0.0.1:0")?.handle(MockHandler(state.clone()))?;
+        Ok(MockBridge { state, listening })
+    }
+    /// The address of the mock bridge, to be used as the IP of a `Bridge`
+    pub fn ip(&self) -> String {
+        self.listening.socket.to_string()
+    }
+    /// Presses the link button, allowing users to be registered for the next 30 seconds
+    pub fn press_link_button(&self) {
+        self.state.lock().unwrap().link_pressed = Some(Instant::now());
+    }
+    /// Adds a user to the whitelist without the link button being pressed
+    pub fn add_user(&self, username: &str, devicetype: &str) {
+        self.state.lock().unwrap().add_user(username, devicetype);
+    }
+    /// The current state of the mock bridge
+    pub fn full_state(&self) -> Result<FullState> {
+        Ok(from_value(self.state.lock().unwrap().full_view())?)
+    }
+}
+
+impl Drop for MockBridge {
+    fn drop(&mut self) {
+        // Dropping `Listening` waits for the server, which never stops
+        let _ = self.listening.close();
+    }
+}
+
+struct MockHandler(Arc<Mutex<State>>);
+
+impl Handler for MockHandler {
+    fn handle<'a, 'k>(&'a self, mut req: Request<'a, 'k>, mut res: Response<'a, Fresh>) {
+        let method = match req.method {
+            HyperMethod::Get => Some(Method::Get),
+            HyperMethod::Post => Some(Method::Post),
+            HyperMethod::Put => Some(Method::Put),
+            HyperMethod::Delete => Some(Method::Delete),
+            _ => None,
+        };
+        let path = match req.uri {
+            RequestUri::AbsolutePath(ref p) => p.split('?').next().unwrap_or("").to_owned(),
+            _ => String::new(),
+        };
+        let mut body = Vec::new();
+        let _ = req.read_to_end(&mut body);
+
+        let reply = self.0.lock().unwrap().handle(method, &path, &body);
+        res.headers_mut().set(ContentType::json());
+        let _ = res.send(&to_vec(&reply).unwrap_or_default());
+    }
+}
+
+struct State {
+    full: Object,
+    link_pressed: Option<Instant>,
+    group0: Object,
+    created: u64,
+}
+
+fn success<V: Into<JsonValue>>(address: String, value: V) -> JsonValue {
+    let mut inner = Object::new();
+    inner.insert(address, value.into());
+    let mut outer = Object::new();
+    outer.insert("success".to_owned(), JsonValue::Object(inner));
+    JsonValue::Object(outer)
+}
+
+fn error(code: u16, address: &str, description: String) -> JsonValue {
+    let mut inner = Object::new();
+    inner.insert("type".to_owned(), code.into());
+    inner.insert("address".to_owned(), address.into());
+    inner.insert("description".to_owned(), description.into());
+    let mut outer = Object::new();
+    outer.insert("error".to_owned(), JsonValue::Object(inner));
+    JsonValue::Object(outer)
+}
+
+fn deleted(address: &str) -> JsonValue {
+    let mut reply = Object::new();
+    reply.insert("success".to_owned(), format!("{} deleted", address).into());
+    JsonValue::Array(vec![JsonValue::Object(reply)])
+}
+
+fn not_available(address: &str) -> JsonValue {
+    JsonValue::Array(vec![error(3, address, format!("resource, {}, not available", address))])
+}
+
+fn now() -> JsonValue {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string().into()
+}
+
+impl State {
+    fn handle(&mut self, method: Option<Method>, path: &str, body: &[u8]) -> JsonValue {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let address = format!("/{}", segments.iter().skip(2).cloned().collect::<Vec<_>>().join("/"));
+        let method = match method {
+            Some(method) => method,
+            None => {
+                return JsonValue::Array(vec![error(4, &address,
+                                                   format!("method not available for resource, {}", address))])
+            }
+        };
+        let body = if body.is_empty() {
+            Object::new()
+        } else {
+            match from_slice(body) {
+                Ok(JsonValue::Object(body)) => body,
+                _ => return JsonValue::Array(vec![error(2, &address, "body contains invalid json".to_owned())]),
+            }
+        };
+
+        match (method, &segments[..]) {
+            (Method::Post, ["api"]) => return self.register(&body),
+            (_, ["api", username, ..]) if self.is_whitelisted(username) => {
+                let username = username.to_string();
+                if let Some(user) = self.config_mut()["whitelist"].get_mut(&username) {
+                    user["last use date"] = now();
+                }
+            }
+            (_, ["api", ..]) => return JsonValue::Array(vec![error(1, "/", "unauthorized user".to_owned())]),
+            _ => return not_available(path),
+        }
+
+        use self::Method::*;
+        match (method, &segments[2..]) {
+            (Get, []) => self.full_view(),
+            (Get, ["config"]) => self.config_view(),
+            (Put, ["config"]) => self.modify_config(&body),
+            (Delete, ["config", "whitelist", user]) => {
+                match self.config_mut()["whitelist"].as_object_mut().and_then(|w| w.remove(*user)) {
+                    Some(_) => deleted(&address),
+                    None => not_available(&address),
+                }
+            }
+            (Get, ["lights", "new"]) => {
+                let mut new = Object::new();
+                new.insert("lastscan".to_owned(), "none".into());
+                JsonValue::Object(new)
+            }
+            (Post, ["lights"]) => JsonValue::Array(vec![success(address, "Searching for new devices")]),
+            (Put, ["lights", id]) => self.rename(&format!("/lights/{}", id), "lights", id, &body),
+            (Delete, ["lights", id]) => self.delete_light(id),
+            (Put, ["lights", id, "state"]) => self.set_light_state(id, &body),
+            (Get, ["groups"]) => self.groups_view(),
+            (Get, ["groups", id]) => self.group_view(id).unwrap_or_else(|| not_available(&address)),
+            (Post, ["groups"]) => self.create_group(&body),
+            (Put, ["groups", id]) => self.modify_group(id, &body),
+            (Put, ["groups", id, "action"]) => self.set_group_action(id, &body),
+            (Delete, ["groups", id]) if *id != "0" => self.delete("groups", id),
+            (Get, ["scenes"]) => self.scenes_view(),
+            (Get, ["scenes", id]) => self.full["scenes"].get(*id).cloned().unwrap_or_else(|| not_available(&address)),
+            (Post, ["scenes"]) => self.create_scene(segments[1], &body),
+            (Put, ["scenes", id]) => self.modify_scene(id, &body),
+            (Put, ["scenes", id, "lightstates", light]) => self.set_scene_lightstate(id, light, &body),
+            (Delete, ["scenes", id]) => self.delete("scenes", id),
+            (Get, _) => {
+                let full = self.full_view();
+                let mut value = &full;
+                for segment in &segments[2..] {
+                    value = match value.get(*segment) {
+                        Some(value) => value,
+                        None => return not_available(&address),
+                    };
+                }
+                value.clone()
+            }
+            _ => {
+                JsonValue::Array(vec![error(4, &address,
+                                            format!("method, {}, not available for resource, {}",
+                                                    method.as_str(), address))])
+            }
+        }
+    }
+
+    fn config(&self) -> &JsonValue {
+        &self.full["config"]
+    }
+
+    fn config_mut(&mut self) -> &mut JsonValue {
+        self.full.get_mut("config").unwrap()
+    }
+
+    fn resources(&mut self, kind: &str) -> &mut Object {
+        self.full.get_mut(kind).and_then(JsonValue::as_object_mut).unwrap()
+    }
+
+    fn is_whitelisted(&self, username: &str) -> bool {
+        self.config()["whitelist"].get(username).is_some()
+    }
+
+    fn link_button_pressed(&self) -> bool {
+        self.link_pressed.is_some_and(|t| t.elapsed() < LINK_BUTTON_WINDOW)
+    }
+
+    fn next_id(&self, kind: &str) -> String {
+        let taken = &self.full[kind];
+        (1..).map(|i: u32| i.to_string()).find(|id| taken.get(id).is_none()).unwrap()
+    }
+
+    fn add_user(&mut self, username: &str, devicetype: &str) {
+        let mut user = Object::new();
+        user.insert("name".to_owned(), devicetype.into());
+        user.insert("create date".to_owned(), now());
+        user.insert("last use date".to_owned(), now());
+        if let Some(whitelist) = self.config_mut()["whitelist"].as_object_mut() {
+            whitelist.insert(username.to_owned(), JsonValue::Object(user));
+        }
+    }
+
+    fn register(&mut self, body: &Object) -> JsonValue {
+        let devicetype = match body.get("devicetype").and_then(JsonValue::as_str) {
+            Some(devicetype) => devicetype.to_owned(),
+            None => return JsonValue::Array(vec![error(5, "/", "invalid/missing parameters in body".to_owned())]),
+        };
+        if !self.link_button_pressed() {
+            return JsonValue::Array(vec![error(101, "", "link button not pressed".to_owned())]);
+        }
+        self.created += 1;
+        let username = format!("mockuser{:032x}", self.created);
+        self.add_user(&username, &devicetype);
+        JsonValue::Array(vec![success("username".to_owned(), username)])
+    }
+
+    fn full_view(&self) -> JsonValue {
+        let mut full = self.full.clone();
+        full.insert("config".to_owned(), self.config_view());
+        full.insert("groups".to_owned(), self.groups_view());
+        full.insert("scenes".to_owned(), self.scenes_view());
+        JsonValue::Object(full)
+    }
+
+    fn config_view(&self) -> JsonValue {
+        let mut config = self.config().clone();
+        config["linkbutton"] = self.link_button_pressed().into();
+        config["UTC"] = now();
+        config["localtime"] = now();
+        config
+    }
+
+    fn modify_config(&mut self, body: &Object) -> JsonValue {
+        const FIXED: &[&str] = &["whitelist", "apiversion", "swversion", "mac", "modelid", "bridgeid",
+                                 "factorynew", "replacesbridgeid", "UTC", "localtime"];
+        let mut replies = Vec::new();
+        for (key, value) in body {
+            let address = format!("/config/{}", key);
+            if FIXED.contains(&&**key) {
+                replies.push(error(8, &address, format!("parameter, {}, is not modifiable", key)));
+            } else if key == "linkbutton" {
+                if value.as_bool() == Some(true) {
+                    self.link_pressed = Some(Instant::now());
+                }
+                replies.push(success(address, value.clone()));
+            } else if self.config().get(key).is_some() {
+                self.config_mut()[key] = value.clone();
+                replies.push(success(address, value.clone()));
+            } else {
+                replies.push(error(6, &address, format!("parameter, {}, not available", key)));
+            }
+        }
+        JsonValue::Array(replies)
+    }
+
+    fn rename(&mut self, address: &str, kind: &str, id: &str, body: &Object) -> JsonValue {
+        let resource = match self.resources(kind).get_mut(id) {
+            Some(resource) => resource,
+            None => return not_available(address),
+        };
+        let replies = body.iter()
+            .map(|(key, value)| {
+                if key == "name" && value.is_string() {
+                    resource["name"] = value.clone();
+                    success(format!("{}/name", address), value.clone())
+                } else {
+                    error(6, &format!("{}/{}", address, key), format!("parameter, {}, not available", key))
+                }
+            })
+            .collect();
+        JsonValue::Array(replies)
+    }
+
+    fn delete(&mut self, kind: &str, id: &str) -> JsonValue {
+        let address = format!("/{}/{}", kind, id);
+        match self.resources(kind).remove(id) {
+            Some(_) => deleted(&address),
+            None => not_available(&address),
+        }
+    }
+
+    fn delete_light(&mut self, id: &str) -> JsonValue {
+        let reply = self.delete("lights", id);
+        for kind in ["groups", "scenes"].iter() {
+            for resource in self.resources(kind).values_mut() {
+                if let Some(lights) = resource["lights"].as_array_mut() {
+                    lights.retain(|l| l.as_str() != Some(id));
+                }
+                if let Some(states) = resource.get_mut("lightstates").and_then(JsonValue::as_object_mut) {
+                    states.remove(id);
+                }
+            }
+        }
+        reply
+    }
+
+    fn set_light_state(&mut self, id: &str, body: &Object) -> JsonValue {
+        let address = format!("/lights/{}/state", id);
+        let state = match self.resources("lights").get_mut(id).and_then(|l| l.get_mut("state")) {
+            Some(&mut JsonValue::Object(ref mut state)) => state,
+            _ => return not_available(&format!("/lights/{}", id)),
+        };
+        // "on" goes first, so the rest of the command can depend on it
+        let mut keys: Vec<&String> = body.keys().collect();
+        keys.sort_by_key(|k| *k != "on");
+
+        let mut replies = Vec::new();
+        for key in keys {
+            let attr_address = format!("{}/{}", address, key);
+            let is_off = state.get("on") == Some(&JsonValue::Bool(false));
+            if is_off && !["on", "alert", "transitiontime"].contains(&&**key) {
+                replies.push(error(201, &attr_address,
+                                   format!("parameter, {}, is not modifiable. Device is set to off.", key)));
+                continue;
+            }
+            replies.push(match apply_attribute(state, key, &body[key]) {
+                Ok((attr, value)) => success(format!("{}/{}", address, attr), value),
+                Err((code, description)) => error(code, &attr_address, description),
+            });
+        }
+        JsonValue::Array(replies)
+    }
+
+    fn group_lights(&self, id: &str) -> Option<Vec<String>> {
+        if id == "0" {
+            Some(self.full["lights"].as_object().unwrap().keys().cloned().collect())
+        } else {
+            self.full["groups"].get(id).map(|g| {
+                g["lights"].as_array()
+                    .map(|ls| ls.iter().filter_map(|l| l.as_str().map(ToOwned::to_owned)).collect())
+                    .unwrap_or_default()
+            })
+        }
+    }
+
+    fn group_view(&self, id: &str) -> Option<JsonValue> {
+        let lights = self.group_lights(id)?;
+        let mut group = if id == "0" {
+            let mut group = Object::new();
+            group.insert("name".to_owned(), "Group 0".into());
+            group.insert("type".to_owned(), "LightGroup".into());
+            group.insert("action".to_owned(), JsonValue::Object(self.group0.clone()));
+            JsonValue::Object(group)
+        } else {
+            self.full["groups"][id].clone()
+        };
+        let on: Vec<bool> = lights.iter()
+            .map(|l| self.full["lights"][l]["state"]["on"].as_bool().unwrap_or(false))
+            .collect();
+        let mut state = Object::new();
+        state.insert("any_on".to_owned(), on.iter().any(|&on| on).into());
+        state.insert("all_on".to_owned(), (!on.is_empty() && on.iter().all(|&on| on)).into());
+        group["state"] = JsonValue::Object(state);
+        group["lights"] = lights.into();
+        Some(group)
+    }
+
+    fn groups_view(&self) -> JsonValue {
+        let groups = self.full["groups"].as_object().unwrap();
+        JsonValue::Object(groups.keys().filter_map(|id| self.group_view(id).map(|g| (id.clone(), g))).collect())
+    }
+
+    fn check_lights(&self, lights: Option<&JsonValue>) -> ::std::result::Result<Vec<JsonValue>, JsonValue> {
+        let lights = match lights.and_then(JsonValue::as_array) {
+            Some(lights) => lights,
+            None => return Err(JsonValue::Array(vec![error(5, "/lights", "invalid/missing parameters in body".to_owned())])),
+        };
+        for light in lights {
+            let id = light.as_str().unwrap_or("");
+            if self.full["lights"].get(id).is_none() {
+                return Err(not_available(&format!("/lights/{}", id)));
+            }
+        }
+        Ok(lights.clone())
+    }
+
+    fn create_group(&mut self, body: &Object) -> JsonValue {
+        let lights = match self.check_lights(body.get("lights")) {
+            Ok(lights) => lights,
+            Err(reply) => return reply,
+        };
+        let id = self.next_id("groups");
+        let mut group = Object::new();
+        group.insert("name".to_owned(), body.get("name").cloned().unwrap_or_else(|| format!("Group {}", id).into()));
+        group.insert("lights".to_owned(), lights.into());
+        group.insert("type".to_owned(), body.get("type").cloned().unwrap_or_else(|| "LightGroup".into()));
+        group.insert("action".to_owned(), JsonValue::Object(default_action()));
+        if let Some(class) = body.get("class") {
+            group.insert("class".to_owned(), class.clone());
+        }
+        self.resources("groups").insert(id.clone(), JsonValue::Object(group));
+        JsonValue::Array(vec![success("id".to_owned(), id)])
+    }
+
+    fn modify_group(&mut self, id: &str, body: &Object) -> JsonValue {
+        let address = format!("/groups/{}", id);
+        if self.full["groups"].get(id).is_none() {
+            return not_available(&address);
+        }
+        let mut replies = Vec::new();
+        for (key, value) in body {
+            let value = match &**key {
+                "lights" => match self.check_lights(Some(value)) {
+                    Ok(lights) => JsonValue::Array(lights),
+                    Err(reply) => return reply,
+                },
+                "name" | "class" => value.clone(),
+                _ => {
+                    replies.push(error(6, &format!("{}/{}", address, key), format!("parameter, {}, not available", key)));
+                    continue;
+                }
+            };
+            self.resources("groups").get_mut(id).unwrap()[key] = value.clone();
+            replies.push(success(format!("{}/{}", address, key), value));
+        }
+        JsonValue::Array(replies)
+    }
+
+    fn set_group_action(&mut self, id: &str, body: &Object) -> JsonValue {
+        let address = format!("/groups/{}/action", id);
+        let lights = match self.group_lights(id) {
+            Some(lights) => lights,
+            None => return not_available(&format!("/groups/{}", id)),
+        };
+        let mut replies = Vec::new();
+        for (key, value) in body {
+            if key == "scene" {
+                let scene = value.as_str().unwrap_or("");
+                let states = match self.full["scenes"].get(scene) {
+                    Some(scene) => scene["lightstates"].as_object().cloned().unwrap_or_default(),
+                    None => return not_available(&format!("/scenes/{}", scene)),
+                };
+                for (light, state) in states.iter().filter(|&(l, _)| lights.contains(l)) {
+                    if let Some(&mut JsonValue::Object(ref mut light)) = self.resources("lights").get_mut(light)
+                        .and_then(|l| l.get_mut("state")) {
+                        for (key, value) in state.as_object().into_iter().flat_map(|s| s.iter()) {
+                            let _ = apply_attribute(light, key, value);
+                        }
+                    }
+                }
+                replies.push(success(format!("{}/scene", address), value.clone()));
+                continue;
+            }
+
+            let mut action = if id == "0" {
+                self.group0.clone()
+            } else {
+                self.full["groups"][id]["action"].as_object().cloned().unwrap_or_else(default_action)
+            };
+            match apply_attribute(&mut action, key, value) {
+                Ok((attr, value)) => {
+                    replies.push(success(format!("{}/{}", address, attr), value));
+                    for light in &lights {
+                        if let Some(&mut JsonValue::Object(ref mut light)) = self.resources("lights").get_mut(light)
+                            .and_then(|l| l.get_mut("state")) {
+                            let _ = apply_attribute(light, key, &body[key]);
+                        }
+                    }
+                }
+                Err((code, description)) => replies.push(error(code, &format!("{}/{}", address, key), description)),
+            }
+            if id == "0" {
+                self.group0 = action;
+            } else {
+                self.resources("groups").get_mut(id).unwrap()["action"] = JsonValue::Object(action);
+            }
+        }
+        JsonValue::Array(replies)
+    }
+
+    fn scenes_view(&self) -> JsonValue {
+        let mut scenes = self.full["scenes"].clone();
+        for scene in scenes.as_object_mut().unwrap().values_mut() {
+            if let Some(scene) = scene.as_object_mut() {
+                scene.remove("lightstates");
+            }
+        }
+        scenes
+    }
+
+    fn current_lightstates(&self, lights: &[JsonValue]) -> JsonValue {
+        let mut states = Object::new();
+        for light in lights.iter().filter_map(JsonValue::as_str) {
+            let state = &self.full["lights"][light]["state"];
+            let mut stored = Object::new();
+            for key in ["on", "bri"].iter() {
+                if let Some(value) = state.get(*key) {
+                    stored.insert(key.to_string(), value.clone());
+                }
+            }
+            let color: &[&str] = match state["colormode"].as_str() {
+                Some("xy") => &["xy"],
+                Some("ct") => &["ct"],
+                Some("hs") => &["hue", "sat"],
+                _ => &[],
+            };
+            for key in color {
+                stored.insert(key.to_string(), state[*key].clone());
+            }
+            states.insert(light.to_owned(), JsonValue::Object(stored));
+        }
+        JsonValue::Object(states)
+    }
+
+    fn create_scene(&mut self, owner: &str, body: &Object) -> JsonValue {
+        let lights = match self.check_lights(body.get("lights")) {
+            Ok(lights) => lights,
+            Err(reply) => return reply,
+        };
+        let name = match body.get("name") {
+            Some(name) => name.clone(),
+            None => return JsonValue::Array(vec![error(5, "/scenes", "invalid/missing parameters in body".to_owned())]),
+        };
+        self.created += 1;
+        let id = format!("mockscene{:06x}", self.created);
+        let mut scene = Object::new();
+        scene.insert("name".to_owned(), name);
+        scene.insert("lightstates".to_owned(), self.current_lightstates(&lights));
+        scene.insert("lights".to_owned(), lights.into());
+        scene.insert("owner".to_owned(), owner.into());
+        scene.insert("recycle".to_owned(), body.get("recycle").cloned().unwrap_or(JsonValue::Bool(false)));
+        scene.insert("locked".to_owned(), false.into());
+        scene.insert("appdata".to_owned(), body.get("appdata").cloned().unwrap_or_else(|| JsonValue::Object(Object::new())));
+        scene.insert("picture".to_owned(), body.get("picture").cloned().unwrap_or_else(|| "".into()));
+        scene.insert("lastupdated".to_owned(), now());
+        self.resources("scenes").insert(id.clone(), JsonValue::Object(scene));
+        JsonValue::Array(vec![success("id".to_owned(), id)])
+    }
+
+    fn modify_scene(&mut self, id: &str, body: &Object) -> JsonValue {
+        let address = format!("/scenes/{}", id);
+        if self.full["scenes"].get(id).is_none() {
+            return not_available(&address);
+        }
+        let mut replies = Vec::new();
+        for (key, value) in body {
+            match &**key {
+                "name" => self.resources("scenes").get_mut(id).unwrap()["name"] = value.clone(),
+                "lights" => {
+                    let lights = match self.check_lights(Some(value)) {
+                        Ok(lights) => lights,
+                        Err(reply) => return reply,
+                    };
+                    let states = self.current_lightstates(&lights);
+                    let scene = self.resources("scenes").get_mut(id).unwrap();
+                    scene["lights"] = lights.into();
+                    scene["lightstates"] = states;
+                }
+                "storelightstate" => {
+                    let lights = self.full["scenes"][id]["lights"].as_array().cloned().unwrap_or_default();
+                    let states = self.current_lightstates(&lights);
+                    self.resources("scenes").get_mut(id).unwrap()["lightstates"] = states;
+                }
+                _ => {
+                    replies.push(error(6, &format!("{}/{}", address, key), format!("parameter, {}, not available", key)));
+                    continue;
+                }
+            }
+            replies.push(success(format!("{}/{}", address, key), value.clone()));
+        }
+        self.resources("scenes").get_mut(id).unwrap()["lastupdated"] = now();
+        JsonValue::Array(replies)
+    }
+
+    fn set_scene_lightstate(&mut self, id: &str, light: &str, body: &Object) -> JsonValue {
+        let address = format!("/scenes/{}/lightstates/{}", id, light);
+        let states = match self.resources("scenes").get_mut(id).and_then(|s| s.get_mut("lightstates")) {
+            Some(&mut JsonValue::Object(ref mut states)) => states,
+            _ => return not_available(&format!("/scenes/{}", id)),
+        };
+        let state = match states.get_mut(light) {
+            Some(&mut JsonValue::Object(ref mut state)) => state,
+            _ => return not_available(&address),
+        };
+        let mut replies = Vec::new();
+        for (key, value) in body {
+            replies.push(match apply_attribute(state, key, value) {
+                Ok((attr, value)) => success(format!("{}/{}", address, attr), value),
+                Err((code, description)) => error(code, &format!("{}/{}", address, key), description),
+            });
+        }
+        JsonValue::Array(replies)
+    }
+}
+
+/// Changes one attribute of a light state like the bridge does, giving the changed attribute and its new value
+fn apply_attribute(state: &mut Object, key: &str, value: &JsonValue) -> ::std::result::Result<(String, JsonValue), (u16, String)> {
+    let invalid = || (7, format!("invalid value, {}, for parameter, {}", value, key));
+    let in_range = |n: f64| attribute_range(key).is_some_and(|(min, max)| n >= min && n <= max);
+    let attr = key.trim_end_matches("_inc");
+    let increment = attr != key;
+    if key == "transitiontime" {
+        return match value.as_u64() {
+            Some(t) if in_range(t as f64) => Ok((key.to_owned(), value.clone())),
+            _ => Err(invalid()),
+        };
+    }
+    if key != "alert" && !state.contains_key(attr) {
+        return Err((6, format!("parameter, {}, not available", key)));
+    }
+
+    let new_value: JsonValue = match (attr, increment) {
+        ("on", false) => value.as_bool().ok_or_else(invalid)?.into(),
+        ("alert", false) => match value.as_str() {
+            Some("none") | Some("select") | Some("lselect") => value.clone(),
+            _ => return Err(invalid()),
+        },
+        ("effect", false) => match value.as_str() {
+            Some("none") | Some("colorloop") => value.clone(),
+            _ => return Err(invalid()),
+        },
+        ("bri", _) | ("sat", _) | ("hue", _) | ("ct", _) => {
+            let (min, max) = attribute_range(attr).map(|(min, max)| (min as i64, max as i64)).ok_or_else(invalid)?;
+            let n = value.as_i64().ok_or_else(invalid)?;
+            if !in_range(n as f64) {
+                return Err(invalid());
+            }
+            if increment {
+                let old = state[attr].as_i64().unwrap_or(min);
+                if attr == "hue" {
+                    (old + n).rem_euclid(max + 1).into()
+                } else {
+                    (old + n).clamp(min, max).into()
+                }
+            } else {
+                n.into()
+            }
+        }
+        ("xy", _) => {
+            let xy = value.as_array()
+                .filter(|xy| xy.len() == 2)
+                .and_then(|xy| Some((xy[0].as_f64()?, xy[1].as_f64()?)))
+                .ok_or_else(invalid)?;
+            if !in_range(xy.0) || !in_range(xy.1) {
+                return Err(invalid());
+            }
+            let (x, y) = if increment {
+                let (min, max) = attribute_range("xy").ok_or_else(invalid)?;
+                let old = &state["xy"];
+                ((old[0].as_f64().unwrap_or(0.) + xy.0).clamp(min, max), (old[1].as_f64().unwrap_or(0.) + xy.1).clamp(min, max))
+            } else {
+                xy
+            };
+            vec![x, y].into()
+        }
+        _ => return Err((6, format!("parameter, {}, not available", key))),
+    };
+
+    let colormode = match attr {
+        "xy" => Some("xy"),
+        "ct" => Some("ct"),
+        "hue" | "sat" => Some("hs"),
+        _ => None,
+    };
+    if let Some(mode) = colormode {
+        if state.contains_key("colormode") {
+            state.insert("colormode".to_owned(), mode.into());
+        }
+    }
+    state.insert(attr.to_owned(), new_value.clone());
+    Ok((attr.to_owned(), new_value))
+}
+
+fn default_action() -> Object {
+    let action = ::serde_json::json!({
+        "on": false, "bri": 254, "hue": 8418, "sat": 140, "xy": [0.4573, 0.41], "ct": 366,
+        "alert": "none", "effect": "none", "colormode": "ct"
+    });
+    match action {
+        JsonValue::Object(action) => action,
+        _ => unreachable!(),
+    }
+}
+
+fn default_lights() -> JsonValue {
+    ::serde_json::json!({
+        "lights": {
+            "1": {
+                "name": "Hue color lamp 1", "type": "Extended color light", "modelid": "LCT015",
+                "manufacturername": "Philips", "swversion": "1.29.0_r21169", "uniqueid": "00:17:88:01:00:00:00:01-0b",
+                "state": {
+                    "on": false, "bri": 254, "hue": 8418, "sat": 140, "xy": [0.4573, 0.41], "ct": 366,
+                    "alert": "none", "effect": "none", "colormode": "ct", "reachable": true
+                }
+            },
+            "2": {
+                "name": "Hue white ambiance 1", "type": "Color temperature light", "modelid": "LTW001",
+                "manufacturername": "Philips", "swversion": "1.29.0_r21169", "uniqueid": "00:17:88:01:00:00:00:02-0b",
+                "state": {
+                    "on": false, "bri": 254, "ct": 366, "alert": "none", "colormode": "ct", "reachable": true
+                }
+            }
+        }
+    })
+}
+
+fn default_config() -> JsonValue {
+    ::serde_json::json!({
+        "name": "Mock bridge",
+        "swupdate": {
+            "checkforupdate": false, "devicetypes": {"bridge": false, "lights": []},
+            "updatestate": 0, "url": "", "text": "", "notify": false
+        },
+        "whitelist": {},
+        "apiversion": "1.16.0", "swversion": "1709131301",
+        "proxyaddress": "none", "proxyport": 0, "linkbutton": false,
+        "ipaddress": "127.0.0.1", "mac": "00:17:88:00:00:00", "netmask": "255.255.255.0",
+        "gateway": "127.0.0.1", "dhcp": true, "portalservices": false,
+        "UTC": "2017-01-01T00:00:00", "localtime": "2017-01-01T00:00:00", "timezone": "UTC",
+        "zigbeechannel": 15, "modelid": "BSB002", "bridgeid": "001788FFFE000000",
+        "factorynew": false, "replacesbridgeid": null
+    })
+}
+
+#[test]
+fn mock_bridge() {
+    use bridge::{self, Bridge};
+    use errors::{HueErrorKind, BridgeError};
+    use ::hue::{LightCommand, LightId, GroupId, GroupType, SceneCreater};
+
+    let mock = MockBridge::start().unwrap();
+    match bridge::register_user(&mock.ip(), "test#mock") {
+        Err(::errors::HueError(HueErrorKind::BridgeError { error: BridgeError::LinkButtonNotPressed, .. }, _)) => (),
+        other => panic!("expected LinkButtonNotPressed, got {:?}", other),
+    }
+    mock.press_link_button();
+    let username = bridge::register_user(&mock.ip(), "test#mock").unwrap();
+    let bridge = Bridge::new(mock.ip(), username.clone());
+
+    assert!(Bridge::new(mock.ip(), "intruder").get_all_lights().is_err());
+    let config = bridge.get_configuration().unwrap();
+    assert!(config.whitelist.contains_key(&username));
+
+    let (one, two) = (LightId::from(1), LightId::from(2));
+    assert_eq!(bridge.get_all_lights().unwrap().len(), 2);
+    // The light is off, so only turning it on succeeds
+    let resp = bridge.set_light_state(one, &LightCommand::default().with_bri(100)).unwrap();
+    assert_eq!(resp.failed_attributes(), ["bri"]);
+    assert!(resp.into_result().is_err());
+    bridge.set_light_state(one, &LightCommand::default().on().with_bri(100)).unwrap();
+    bridge.set_light_state(one, &LightCommand::default().with_bri_inc(-40)).unwrap();
+    assert_eq!(bridge.get_light(one).unwrap().state.bri, 60);
+
+    let group = bridge.create_group("Room".to_owned(), vec![one, two], GroupType::LightGroup, None).unwrap();
+    assert_eq!(bridge.get_group_attributes(group).unwrap().lights, vec![one, two]);
+    bridge.set_group_state(GroupId::ALL, &LightCommand::default().on().with_ct(200)).unwrap();
+    assert_eq!(bridge.get_light(two).unwrap().state.ct, Some(200));
+    assert!(bridge.get_group_attributes(group).unwrap().state.unwrap().all_on);
+
+    let scene = bridge.create_scene(&SceneCreater {
+            name: "Bright".to_owned(),
+            lights: vec![one],
+            recycle: None,
+            appdata: None,
+            picture: None,
+            transitiontime: None,
+        })
+        .unwrap();
+    bridge.set_light_state(one, &LightCommand::default().off()).unwrap();
+    bridge.recall_scene_in_group(GroupId::ALL, &scene).unwrap();
+    assert!(bridge.get_light(one).unwrap().state.on);
+    assert_eq!(bridge.get_scene_with_states(&scene).unwrap().lightstates[&one].ct, Some(200));
+
+    bridge.delete_group(group).unwrap();
+    assert!(bridge.get_group_attributes(group).is_err());
+    assert_eq!(mock.full_state().unwrap().scenes.len(), 1);
+
+    bridge.delete_user(&username).unwrap();
+    assert!(bridge.get_all_lights().is_err());
+}
@@ -0,0 +1,210 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use errors::{Result, HueErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What to do with a command when its budget is used up
+pub enum LimitMode {
+    /// Wait until the command may be sent, however long that takes
+    Block,
+    /// Wait in line until the command may be sent, unless this many commands are already waiting,
+    /// in which case it fails with `HueErrorKind::RateLimited`. `Queue(0)` is the same as `FailFast`.
+    Queue(usize),
+    /// Fail with `HueErrorKind::RateLimited` without sending the command
+    FailFast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How many commands may be sent
+pub struct Budget {
+    /// The number of commands per second that may be sent in the long run
+    pub per_second: f64,
+    /// The number of commands that may be sent at once after a quiet period
+    pub burst: u32,
+}
+
+impl Budget {
+    /// The budget for light commands recommended by Philips
+    pub const LIGHTS: Budget = Budget {
+        per_second: 10.,
+        burst: 10,
+    };
+    /// The budget for group commands recommended by Philips
+    pub const GROUPS: Budget = Budget {
+        per_second: 1.,
+        burst: 1,
+    };
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// The tokens left, negative when commands are waiting for tokens
+    tokens: f64,
+    /// When the tokens were last counted
+    counted: Instant,
+    /// How many commands are waiting for their token
+    waiting: usize,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    budget: Budget,
+    state: Mutex<BucketState>,
+}
+
+impl Bucket {
+    fn new(budget: Budget) -> Self {
+        Bucket {
+            budget,
+            state: Mutex::new(BucketState {
+                tokens: budget.burst as f64,
+                counted: Instant::now(),
+                waiting: 0,
+            }),
+        }
+    }
+    /// Takes a token, returning how long to wait before using it.
+    ///
+    /// A command that has to wait counts as waiting until `done_waiting()` is called.
+    fn take(&self, mode: LimitMode, address: &str) -> Result<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let refilled = now.duration_since(state.counted).as_secs_f64() * self.budget.per_second;
+        state.tokens = (state.tokens + refilled).min(self.budget.burst as f64);
+        state.counted = now;
+
+        if state.tokens < 1. {
+            let full = match mode {
+                LimitMode::Block => false,
+                LimitMode::Queue(max) => state.waiting >= max,
+                LimitMode::FailFast => true,
+            };
+            if full {
+                return Err(HueErrorKind::RateLimited(address.to_owned()).into());
+            }
+        }
+        state.tokens -= 1.;
+        let wait = Duration::from_secs_f64((-state.tokens).max(0.) / self.budget.per_second);
+        if wait > Duration::from_secs(0) {
+            state.waiting += 1;
+        }
+        Ok(wait)
+    }
+    fn done_waiting(&self) {
+        self.state.lock().unwrap().waiting -= 1;
+    }
+    /// Waits until a command may be sent, or fails if the mode says so
+    fn acquire(&self, mode: LimitMode, address: &str) -> Result<()> {
+        let wait = self.take(mode, address)?;
+        if wait > Duration::from_secs(0) {
+            thread::sleep(wait);
+            self.done_waiting();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+/// Limits how fast commands are sent to lights and groups, so that the bridge doesn't drop them.
+///
+/// Light commands and group commands have separate budgets, which are refilled continuously
+/// like a [token bucket](https://en.wikipedia.org/wiki/Token_bucket).
+pub struct RateLimiter {
+    lights: Bucket,
+    groups: Bucket,
+    mode: LimitMode,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(LimitMode::Block)
+    }
+}
+
+impl RateLimiter {
+    /// Creates a limiter with the budgets recommended by Philips
+    pub fn new(mode: LimitMode) -> Self {
+        RateLimiter {
+            lights: Bucket::new(Budget::LIGHTS),
+            groups: Bucket::new(Budget::GROUPS),
+            mode,
+        }
+    }
+    /// Creates a limiter with custom budgets.
+    ///
+    /// Fails with `HueErrorKind::InvalidBudget` unless both budgets allow a positive number of commands
+    /// per second and a burst of at least one.
+    pub fn with_budgets(lights: Budget, groups: Budget, mode: LimitMode) -> Result<Self> {
+        for budget in [lights, groups].iter() {
+            if !(budget.per_second > 0. && budget.per_second.is_finite()) || budget.burst == 0 {
+                return Err(HueErrorKind::InvalidBudget(format!("{:?}", budget)).into());
+            }
+        }
+        Ok(RateLimiter {
+            lights: Bucket::new(lights),
+            groups: Bucket::new(groups),
+            mode,
+        })
+    }
+    /// Waits until a command may be sent to the light, or fails if the mode says so
+    pub fn acquire_light(&self, address: &str) -> Result<()> {
+        self.lights.acquire(self.mode, address)
+    }
+    /// Waits until a command may be sent to the group, or fails if the mode says so
+    pub fn acquire_group(&self, address: &str) -> Result<()> {
+        self.groups.acquire(self.mode, address)
+    }
+}
+
+#[test]
+fn rate_limits() {
+    let budget = Budget {
+        per_second: 100.,
+        burst: 2,
+    };
+
+    let limiter = RateLimiter::with_budgets(budget, budget, LimitMode::FailFast).unwrap();
+    assert!(limiter.acquire_light("/lights/1/state").is_ok());
+    assert!(limiter.acquire_light("/lights/1/state").is_ok());
+    assert!(limiter.acquire_light("/lights/1/state").is_err());
+    // Groups have their own budget
+    assert!(limiter.acquire_group("/groups/0/action").is_ok());
+
+    let limiter = RateLimiter::with_budgets(budget, budget, LimitMode::Queue(1)).unwrap();
+    let bucket = &limiter.lights;
+    assert_eq!(bucket.take(LimitMode::Queue(1), "").unwrap(), Duration::from_secs(0));
+    assert_eq!(bucket.take(LimitMode::Queue(1), "").unwrap(), Duration::from_secs(0));
+    let wait = bucket.take(LimitMode::Queue(1), "").unwrap();
+    assert!(wait > Duration::from_millis(5) && wait <= Duration::from_millis(10));
+    assert!(bucket.take(LimitMode::Queue(1), "").is_err());
+    let wait = bucket.take(LimitMode::Block, "").unwrap();
+    assert!(wait > Duration::from_millis(15) && wait <= Duration::from_millis(20));
+}
+
+#[test]
+fn bounded_queue() {
+    use std::sync::Arc;
+
+    let budget = Budget {
+        per_second: 20.,
+        burst: 1,
+    };
+    let limiter = Arc::new(RateLimiter::with_budgets(budget, budget, LimitMode::Queue(1)).unwrap());
+    assert!(limiter.acquire_light("/lights/1/state").is_ok());
+    let waiter = {
+        let limiter = limiter.clone();
+        thread::spawn(move || limiter.acquire_light("/lights/1/state"))
+    };
+    thread::sleep(Duration::from_millis(20));
+    // One command is waiting, which is all the queue allows
+    assert!(limiter.acquire_light("/lights/1/state").is_err());
+    assert!(waiter.join().unwrap().is_ok());
+    assert!(limiter.acquire_light("/lights/1/state").is_ok());
+
+    let empty = Budget { per_second: 0., burst: 1 };
+    assert!(RateLimiter::with_budgets(budget, empty, LimitMode::Block).is_err());
+    assert!(RateLimiter::with_budgets(Budget { burst: 0, ..budget }, budget, LimitMode::Block).is_err());
+    assert!(RateLimiter::with_budgets(Budget { per_second: f64::NAN, ..budget }, budget, LimitMode::Block).is_err());
+}
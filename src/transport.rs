@@ -0,0 +1,402 @@
+use hyper::Client;
+use hyper::client::Body;
+use hyper::client::pool::Pool;
+use hyper::method::Method as HyperMethod;
+use hyper::net::{NetworkConnector, HttpStream};
+
+use std::io::{self, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(any(feature = "https", test))]
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::from_slice;
+
+use errors::{Result, HueError};
+use json::HueResponse;
+use ::hue::{JsonMap, JsonValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The HTTP methods used by the Hue API
+pub enum Method {
+    /// Gets a resource
+    Get,
+    /// Creates a resource
+    Post,
+    /// Modifies a resource
+    Put,
+    /// Deletes a resource
+    Delete,
+}
+
+impl Method {
+    /// The name of the method as used in HTTP
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A response to a request sent through a `Transport`
+pub struct Response {
+    /// The HTTP status code
+    pub status: u16,
+    /// The body of the response, usually JSON
+    pub body: Vec<u8>,
+}
+
+/// Sends HTTP requests to the bridge on behalf of a `Bridge`.
+///
+/// The transport knows how to reach the bridge, such as its address and whether to use HTTPS,
+/// and is only given the path of each request. Implement this to use another HTTP stack, or to fake the bridge in tests.
+pub trait Transport {
+    /// Sends a request for the path of the resource, such as "/api/<username>/lights", and returns the response
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response>;
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        (**self).send(method, path, body)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        (**self).send(method, path, body)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How long a `HyperTransport` waits for the bridge. `None` waits forever.
+pub struct Timeouts {
+    /// How long to wait for the connection to the bridge to be made
+    pub connect: Option<Duration>,
+    /// How long to wait for data from the bridge
+    pub read: Option<Duration>,
+    /// How long to wait for a request to be sent
+    pub write: Option<Duration>,
+}
+
+impl Timeouts {
+    /// The same timeout for connecting, reading and writing
+    pub fn all(timeout: Duration) -> Self {
+        Timeouts {
+            connect: Some(timeout),
+            read: Some(timeout),
+            write: Some(timeout),
+        }
+    }
+}
+
+/// Connects over plain TCP like hyper's `HttpConnector`, but gives up after the timeout
+#[derive(Debug, Clone, Copy)]
+struct TimeoutConnector(Option<Duration>);
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> ::hyper::Result<HttpStream> {
+        if scheme != "http" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid scheme for Http").into());
+        }
+        let timeout = match self.0 {
+            Some(timeout) => timeout,
+            None => return Ok(HttpStream(TcpStream::connect((host, port))?)),
+        };
+        let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, "Could not resolve the host");
+        for addr in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(HttpStream(stream)),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error.into())
+    }
+}
+
+#[derive(Debug)]
+/// The default `Transport`, sending requests to the bridge at an IP with a hyper `Client`
+pub struct HyperTransport {
+    client: Client,
+    base_url: String,
+}
+
+impl HyperTransport {
+    /// Creates a transport for the bridge at `ip` with a default hyper `Client`, which never times out
+    pub fn new<S: Into<String>>(ip: S) -> Self {
+        HyperTransport::with_client(ip, Client::new())
+    }
+    /// Creates a transport for the bridge at `ip` that gives up on requests after the timeouts
+    pub fn with_timeouts<S: Into<String>>(ip: S, timeouts: Timeouts) -> Self {
+        let pool = Pool::with_connector(Default::default(), TimeoutConnector(timeouts.connect));
+        HyperTransport::with_client(ip, client_with_timeouts(Client::with_connector(pool), timeouts))
+    }
+    /// Creates a transport for the bridge at `ip` sending requests with `client` over HTTP
+    pub fn with_client<S: Into<String>>(ip: S, client: Client) -> Self {
+        HyperTransport {
+            client,
+            base_url: format!("http://{}", ip.into()),
+        }
+    }
+    /// The scheme and address the paths of requests are appended to, e.g. "http://192.168.1.20"
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+fn client_with_timeouts(mut client: Client, timeouts: Timeouts) -> Client {
+    client.set_read_timeout(timeouts.read);
+    client.set_write_timeout(timeouts.write);
+    client
+}
+
+#[cfg(any(feature = "https", test))]
+#[derive(Debug, Clone)]
+/// The SHA-256 fingerprint of the certificate an HTTPS transport accepts from the bridge.
+///
+/// Bridges use self-signed certificates, so they can't be checked against certificate authorities.
+/// Instead, the first certificate seen is trusted and every later connection has to present
+/// the same one. Save `fingerprint()` after the first connection, such as registering a user,
+/// and use `CertificatePin::new()` with it from then on. Clones share the pinned fingerprint.
+pub struct CertificatePin(Arc<Mutex<Option<Vec<u8>>>>);
+
+#[cfg(any(feature = "https", test))]
+impl CertificatePin {
+    /// A pin that trusts the first certificate it is shown and then only that one
+    pub fn trust_on_first_use() -> Self {
+        CertificatePin(Arc::new(Mutex::new(None)))
+    }
+    /// A pin that only accepts the certificate with the SHA-256 `fingerprint`
+    pub fn new(fingerprint: Vec<u8>) -> Self {
+        CertificatePin(Arc::new(Mutex::new(Some(fingerprint))))
+    }
+    /// The fingerprint of the pinned certificate, or `None` if no certificate has been seen yet
+    pub fn fingerprint(&self) -> Option<Vec<u8>> {
+        self.0.lock().unwrap().clone()
+    }
+    /// Whether the certificate with `fingerprint` is accepted, pinning it if it's the first one
+    fn accept(&self, fingerprint: &[u8]) -> bool {
+        let mut pinned = self.0.lock().unwrap();
+        match *pinned {
+            Some(ref pinned) => pinned[..] == *fingerprint,
+            None => {
+                *pinned = Some(fingerprint.to_vec());
+                true
+            }
+        }
+    }
+}
+
+#[cfg(feature = "https")]
+impl HyperTransport {
+    /// Creates a transport talking HTTPS to the bridge at `ip` that only accepts the certificate pinned by `pin`,
+    /// which has to have the id `bridgeid` as its common name.
+    ///
+    /// The common name alone proves little, as anyone can make a self-signed certificate with it,
+    /// but it catches talking to the wrong bridge before the first certificate is pinned.
+    pub fn pinned<S: Into<String>>(ip: S, bridgeid: &str, pin: CertificatePin) -> Result<Self> {
+        HyperTransport::pinned_with_timeouts(ip, bridgeid, pin, Timeouts::default())
+    }
+    /// Same as `pinned()`, but gives up on requests after the timeouts
+    pub fn pinned_with_timeouts<S: Into<String>>(ip: S, bridgeid: &str, pin: CertificatePin, timeouts: Timeouts)
+                                                 -> Result<Self> {
+        use hyper::net::HttpsConnector;
+        use hyper_openssl::OpensslClient;
+        use hyper_openssl::openssl::hash::MessageDigest;
+        use hyper_openssl::openssl::nid;
+        use hyper_openssl::openssl::ssl::{SslConnectorBuilder, SslMethod, SSL_VERIFY_PEER};
+
+        let expected = bridgeid.to_lowercase();
+        let mut builder = SslConnectorBuilder::new(SslMethod::tls()).map_err(|e| e.to_string())?;
+        builder.builder_mut().set_verify_callback(SSL_VERIFY_PEER, move |_, ctx| {
+            // The certificate of the bridge is self-signed, so only it is checked and not its issuers
+            if ctx.error_depth() > 0 {
+                return true;
+            }
+            ctx.current_cert().map_or(false, |cert| {
+                let named = cert.subject_name()
+                    .entries_by_nid(nid::COMMONNAME)
+                    .any(|cn| cn.data().as_utf8().map(|cn| cn.to_lowercase() == expected).unwrap_or(false));
+                named && cert.fingerprint(MessageDigest::sha256()).map(|f| pin.accept(&f)).unwrap_or(false)
+            })
+        });
+        let mut ssl = OpensslClient::from(builder.build());
+        // The bridge is reached by its IP, which its certificate doesn't mention
+        ssl.danger_disable_hostname_verification(true);
+        let connector = HttpsConnector::with_connector(ssl, TimeoutConnector(timeouts.connect));
+        Ok(HyperTransport {
+            client: client_with_timeouts(Client::with_connector(connector), timeouts),
+            base_url: format!("https://{}", ip.into()),
+        })
+    }
+}
+
+impl Transport for HyperTransport {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        let method = match method {
+            Method::Get => HyperMethod::Get,
+            Method::Post => HyperMethod::Post,
+            Method::Put => HyperMethod::Put,
+            Method::Delete => HyperMethod::Delete,
+        };
+        let url = format!("{}{}", self.base_url, path);
+        let mut rb = self.client.request(method, &*url);
+        if let Some(body) = body {
+            rb = rb.body(Body::BufBody(body, body.len()));
+        }
+        let mut resp = rb.send()?;
+        let mut buf = Vec::new();
+        resp.read_to_end(&mut buf)?;
+        Ok(Response {
+            status: resp.status.to_u16(),
+            body: buf,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How often and how long apart a `Retrying` transport sends a failed request again
+pub struct RetryPolicy {
+    /// The most times a request is sent again
+    pub max_retries: u32,
+    /// How long to wait before the first retry. The wait doubles with every retry.
+    pub initial_backoff: Duration,
+    /// The longest wait between two retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before the retry with the number `retry`, counting from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .map_or(self.max_backoff, |b| b.min(self.max_backoff))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A `Transport` that sends requests again when they fail with an error that might go away.
+///
+/// Only requests that have the same effect when sent twice are retried, which are GET requests
+/// and PUT requests without increments such as `bri_inc`. See `HueError::is_retryable()` for
+/// which errors are retried.
+pub struct Retrying<T> {
+    transport: T,
+    policy: RetryPolicy,
+}
+
+impl<T: Transport> Retrying<T> {
+    /// Retries the requests sent through `transport` according to `policy`
+    pub fn new(transport: T, policy: RetryPolicy) -> Self {
+        Retrying { transport, policy }
+    }
+    /// The transport the requests are sent through
+    pub fn inner(&self) -> &T {
+        &self.transport
+    }
+}
+
+/// Whether the body of a PUT request changes a value relative to its current value
+fn has_increments(body: &[u8]) -> bool {
+    from_slice::<JsonMap<String, JsonValue>>(body)
+        .map(|m| m.keys().any(|k| k.ends_with("_inc")))
+        .unwrap_or(false)
+}
+
+/// Whether the bridge responded with an error that might go away
+fn has_retryable_error(resp: &Response) -> bool {
+    from_slice::<Vec<HueResponse<JsonValue>>>(&resp.body)
+        .map(|rs| rs.into_iter().filter_map(|r| r.error).any(|e| HueError::from(e).is_retryable()))
+        .unwrap_or(false)
+}
+
+impl<T: Transport> Transport for Retrying<T> {
+    fn send(&self, method: Method, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        let idempotent = match method {
+            Method::Get => true,
+            Method::Put => !body.is_some_and(has_increments),
+            Method::Post | Method::Delete => false,
+        };
+        let mut retry = 0;
+        loop {
+            let result = self.transport.send(method, path, body);
+            let failed = match result {
+                Ok(ref resp) => has_retryable_error(resp),
+                Err(ref e) => e.is_retryable(),
+            };
+            if !idempotent || !failed || retry >= self.policy.max_retries {
+                return result;
+            }
+            thread::sleep(self.policy.backoff(retry));
+            retry += 1;
+        }
+    }
+}
+
+#[test]
+fn retries() {
+    use std::cell::Cell;
+
+    struct Flaky(Cell<u32>);
+    impl Transport for Flaky {
+        fn send(&self, _: Method, _: &str, _: Option<&[u8]>) -> Result<Response> {
+            self.0.set(self.0.get() + 1);
+            match self.0.get() {
+                1 => Err(io::Error::new(io::ErrorKind::TimedOut, "timed out").into()),
+                2 => Ok(Response {
+                    status: 200,
+                    body: br#"[{"error":{"type":901,"address":"/lights","description":"Internal error"}}]"#.to_vec(),
+                }),
+                _ => Ok(Response { status: 200, body: b"{}".to_vec() }),
+            }
+        }
+    }
+
+    let policy = RetryPolicy {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(2),
+    };
+    let t = Retrying::new(Flaky(Cell::new(0)), policy);
+    assert_eq!(t.send(Method::Get, "/lights", None).unwrap().body, b"{}");
+    assert_eq!(t.inner().0.get(), 3);
+
+    t.inner().0.set(0);
+    assert!(t.send(Method::Post, "/lights", None).is_err());
+    assert!(t.send(Method::Put, "/lights/1/state", Some(br#"{"bri_inc":10}"#)).unwrap().body != b"{}");
+    assert_eq!(t.inner().0.get(), 2);
+
+    assert_eq!(policy.backoff(0), Duration::from_millis(1));
+    assert_eq!(policy.backoff(5), Duration::from_millis(2));
+    assert_eq!(RetryPolicy::default().backoff(40), Duration::from_secs(2));
+}
+
+#[test]
+fn certificate_pins() {
+    let pin = CertificatePin::trust_on_first_use();
+    assert_eq!(pin.fingerprint(), None);
+    assert!(pin.clone().accept(b"first"));
+    assert_eq!(pin.fingerprint(), Some(b"first".to_vec()));
+    assert!(pin.accept(b"first"));
+    assert!(!pin.accept(b"other"));
+
+    let pin = CertificatePin::new(b"saved".to_vec());
+    assert!(!pin.accept(b"first"));
+    assert!(pin.accept(b"saved"));
+}